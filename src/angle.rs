@@ -0,0 +1,76 @@
+use std::f64::consts::PI;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum AngleMode {
+    #[default]
+    Radians,
+    Degrees,
+    Gradians,
+}
+
+impl AngleMode {
+    // the order `drg` cycles through
+    pub fn next(self) -> Self {
+        match self {
+            Self::Radians => Self::Degrees,
+            Self::Degrees => Self::Gradians,
+            Self::Gradians => Self::Radians,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Radians => "RAD",
+            Self::Degrees => "DEG",
+            Self::Gradians => "GRAD",
+        }
+    }
+
+    // how many of this unit make a half turn
+    fn half_turn(self) -> f64 {
+        match self {
+            Self::Radians => PI,
+            Self::Degrees => 180.0,
+            Self::Gradians => 200.0,
+        }
+    }
+
+    pub fn to_radians(self, angle: f64) -> f64 {
+        match self {
+            Self::Radians => angle,
+            _ => angle * PI / self.half_turn(),
+        }
+    }
+
+    // where tan has a pole. exact in degrees and gradians; in radians, within rounding of an odd multiple of pi/2
+    pub fn is_odd_right_angle(self, angle: f64) -> bool {
+        match self {
+            Self::Radians => angle.cos().abs() <= angle.abs() * f64::EPSILON,
+            _ => angle.rem_euclid(self.half_turn()) == self.half_turn() / 2.0,
+        }
+    }
+
+    pub fn convert_from_radians(self, angle: f64) -> f64 {
+        match self {
+            Self::Radians => angle,
+            _ => angle * self.half_turn() / PI,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AngleMode;
+    use std::f64::consts::PI;
+
+    // tan's poles are exact in degrees and gradians, and within rounding in radians
+    #[test]
+    fn right_angles_found() {
+        assert!(AngleMode::Degrees.is_odd_right_angle(270.0));
+        assert!(AngleMode::Gradians.is_odd_right_angle(-100.0));
+        assert!(AngleMode::Radians.is_odd_right_angle(3.0 * PI / 2.0));
+        assert!(!AngleMode::Degrees.is_odd_right_angle(180.0));
+        assert!(!AngleMode::Radians.is_odd_right_angle(PI / 4.0));
+        assert!(!AngleMode::Radians.is_odd_right_angle(0.0));
+    }
+}
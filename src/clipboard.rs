@@ -0,0 +1,73 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+// goes through whichever clipboard tool the system has, in this order
+const PASTE_COMMANDS: &[&[&str]] = &[
+    &["pbpaste"],
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+    &["xsel", "--clipboard", "--output"],
+];
+const COPY_COMMANDS: &[&[&str]] = &[
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+pub fn read() -> Result<String, String> {
+    for command in PASTE_COMMANDS {
+        let output = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => return Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+            // a missing tool, or one with no display to talk to, just means trying the next
+            _ => continue,
+        }
+    }
+    Err("No clipboard tool found; install wl-clipboard, xclip, or xsel".into())
+}
+
+// without a clipboard tool, asks the terminal to set the clipboard instead
+pub fn write(text: &str) -> Result<(), String> {
+    for command in COPY_COMMANDS {
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else { continue };
+        // unwrap safety: stdin was piped
+        let written = child.stdin.take().unwrap().write_all(text.as_bytes());
+        if written.is_ok() && child.wait().is_ok_and(|s| s.success()) {
+            return Ok(());
+        }
+    }
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))
+        .and_then(|()| stdout.flush())
+        .map_err(|e| format!("Unable to copy: {e}"))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            // a chunk of k bytes fills k + 1 characters; the rest are padding
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize].into());
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+use ratatui::style::Style;
+
+use crate::theme::Theme;
+
+// everything that ends up in the error panel. the kind decides its color, and batch mode and
+// the selftest can match on it instead of the text
+#[derive(Clone, Debug, PartialEq)]
+pub enum OpError {
+    // too few values on the stack
+    Arity {op: String, needed: usize, found: usize},
+    // the values were there, but the operation can't take them, like a negative factorial
+    Domain(String),
+    LuaError(String),
+    UiuaError(String),
+    // a token that isn't a number or an operation
+    Unknown(String),
+    // anything from outside an operation, like a bad path or an unreadable clipboard
+    Other(String),
+    // not a failure, like `Copied 3`. shown in the text color
+    Notice(String),
+    // the operation worked, but something about it looks wrong
+    Warning(String),
+}
+
+impl fmt::Display for OpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Arity {op, needed, found} => {
+                let operands = if *needed == 1 { "operand" } else { "operands" };
+                write!(f, "{op} needs {needed} {operands}, found {found}")
+            },
+            Self::Unknown(token) => write!(f, "Unknown operation `{token}`"),
+            Self::Domain(message) | Self::LuaError(message) | Self::UiuaError(message)
+                | Self::Other(message) | Self::Notice(message) | Self::Warning(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<String> for OpError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for OpError {
+    fn from(message: &str) -> Self {
+        Self::Other(message.into())
+    }
+}
+
+impl OpError {
+    pub fn style(&self, theme: &Theme) -> Style {
+        match self {
+            Self::Notice(_) => theme.text,
+            Self::LuaError(_) | Self::UiuaError(_) => theme.script_error,
+            Self::Warning(_) => theme.warning,
+            _ => theme.error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpError;
+
+    #[test]
+    fn notices_display_plainly() {
+        assert_eq!(OpError::Notice("Copied 1".into()).to_string(), "Copied 1");
+    }
+}
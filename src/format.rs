@@ -0,0 +1,185 @@
+use crate::number::round_to_significant;
+
+// auto shows this many significant digits, which hides the last bit or two of float error
+// like the 4 in `0.30000000000000004` while still telling apart any two numbers typed in decimal
+pub const AUTO_DIGITS: u32 = 15;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DisplayFormat {
+    Auto,
+    // digits after the decimal point
+    Fixed(usize),
+    // digits after the decimal point of the mantissa
+    Scientific(usize),
+    // exponent is always a multiple of 3
+    Engineering,
+}
+
+impl DisplayFormat {
+    // the selector popped by `fmt`; fixed and scientific also pop a digit count
+    pub fn from_selector(selector: f64, digits: Option<f64>) -> Option<Self> {
+        let digits = || digits.filter(|d| *d >= 0.0 && d.fract() == 0.0 && *d <= 17.0).map(|d| d as usize);
+        if selector.fract() != 0.0 {
+            return None;
+        }
+        match selector as i64 {
+            0 => Some(Self::Auto),
+            1 => digits().map(Self::Fixed),
+            2 => digits().map(Self::Scientific),
+            3 => Some(Self::Engineering),
+            _ => None,
+        }
+    }
+
+    pub fn needs_digits(selector: f64) -> bool {
+        selector == 1.0 || selector == 2.0
+    }
+
+    // short enough for the status bar
+    pub fn name(self) -> String {
+        match self {
+            Self::Auto => "auto".into(),
+            Self::Fixed(digits) => format!("fix {digits}"),
+            Self::Scientific(digits) => format!("sci {digits}"),
+            Self::Engineering => "eng".into(),
+        }
+    }
+
+    pub fn format(self, n: f64) -> String {
+        // these would otherwise come out as things like `inf.000` or `NaNe0`
+        if n.is_nan() {
+            return "NaN".into();
+        }
+        if n.is_infinite() {
+            return if n > 0.0 { "inf".into() } else { "-inf".into() };
+        }
+        match self {
+            // whole numbers come out without a decimal point
+            Self::Auto => format!("{}", round_to_significant(n, AUTO_DIGITS)),
+            Self::Fixed(digits) => format!("{n:.digits$}"),
+            Self::Scientific(digits) => format!("{n:.digits$e}"),
+            Self::Engineering => {
+                if n == 0.0 {
+                    return "0.000e0".into();
+                }
+                let exponent = (n.abs().log10() / 3.0).floor() as i32 * 3;
+                let mantissa = n / 10f64.powi(exponent);
+                format!("{mantissa:.3}e{exponent}")
+            },
+        }
+    }
+}
+
+// the base integers are shown in. each one's prefix parses back as input
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Base {
+    #[default]
+    Decimal,
+    Hexadecimal,
+    Octal,
+    Binary,
+}
+
+impl Base {
+    // the order `base` cycles through
+    pub fn next(self) -> Self {
+        match self {
+            Self::Decimal => Self::Hexadecimal,
+            Self::Hexadecimal => Self::Octal,
+            Self::Octal => Self::Binary,
+            Self::Binary => Self::Decimal,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Decimal => "dec",
+            Self::Hexadecimal => "hex",
+            Self::Octal => "oct",
+            Self::Binary => "bin",
+        }
+    }
+
+    pub fn radix(self) -> u64 {
+        match self {
+            Self::Decimal => 10,
+            Self::Hexadecimal => 16,
+            Self::Octal => 8,
+            Self::Binary => 2,
+        }
+    }
+
+    // None for decimal and for anything that isn't an integer, which are left to DisplayFormat
+    pub fn format(self, n: f64) -> Option<String> {
+        if self == Self::Decimal || n.fract() != 0.0 || n.abs() >= 2f64.powi(64) {
+            return None;
+        }
+        let sign = if n < 0.0 { "-" } else { "" };
+        let magnitude = n.abs() as u64;
+        Some(match self {
+            Self::Decimal => unreachable!(),
+            Self::Hexadecimal => format!("{sign}0x{magnitude:x}"),
+            Self::Octal => format!("{sign}0o{magnitude:o}"),
+            Self::Binary => format!("{sign}0b{magnitude:b}"),
+        })
+    }
+}
+
+// separates the integer digits in already formatted text, like `1 000 000.5`. decimal and octal digits go in
+// threes and hex and binary in fours. digits after a decimal point or in an exponent are left alone
+pub fn group_digits(text: &str, separator: char) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut i = 0;
+    while i < chars.len() {
+        let prefix = (chars[i] == '0').then(|| chars.get(i + 1).copied()).flatten();
+        let (skip, group, is_digit): (usize, usize, fn(&char) -> bool) = match prefix {
+            Some('x') => (2, 4, char::is_ascii_hexdigit),
+            Some('b') => (2, 4, char::is_ascii_digit),
+            Some('o') => (2, 3, char::is_ascii_digit),
+            _ => (0, 3, char::is_ascii_digit),
+        };
+        let before = i.checked_sub(1).map(|i| chars[i]);
+        let exponent = matches!(before, Some('e' | 'E')) || (matches!(before, Some('-' | '+')) && i >= 2 && matches!(chars[i - 2], 'e' | 'E'));
+        // the start of an integer part, not the middle of a run or a fraction
+        if !chars[i].is_ascii_digit() || exponent || matches!(before, Some('.')) || before.is_some_and(|c| c.is_ascii_alphanumeric()) {
+            let end = i + chars[i..].iter().take_while(|c| c.is_ascii_alphanumeric()).count().max(1);
+            out.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+        out.extend(&chars[i..i + skip]);
+        i += skip;
+        let digits = chars[i..].iter().take_while(|c| is_digit(c)).count();
+        for (j, c) in chars[i..i + digits].iter().enumerate() {
+            if j > 0 && (digits - j) % group == 0 {
+                out.push(separator);
+            }
+            out.push(*c);
+        }
+        i += digits;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_digits, DisplayFormat};
+
+    // only integer parts are grouped, in threes, or fours after 0x and 0b
+    #[test]
+    fn digits_grouped() {
+        for (text, grouped) in [("1000000.2345", "1 000 000.2345"), ("-0x1fffff", "-0x1f ffff"), ("0b101010", "0b10 1010"),
+            ("1.5e-1000", "1.5e-1000"), ("[1000 -12345]", "[1 000 -12 345]"), ("1500m", "1 500m"), ("999", "999"), ("1000+2000i", "1 000+2 000i")] {
+            assert_eq!(group_digits(text, ' '), grouped);
+        }
+    }
+
+    // auto hides float noise and never puts a decimal point on a whole number
+    #[test]
+    fn auto_trims_noise() {
+        for (n, text) in [(0.1 + 0.2, "0.3"), (3.0, "3"), (-2.5e-7, "-0.00000025"), (1.0 / 3.0, "0.333333333333333")] {
+            assert_eq!(DisplayFormat::Auto.format(n), text);
+        }
+    }
+}
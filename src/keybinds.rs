@@ -0,0 +1,242 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{CursorMove, Event};
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+#[derive(Clone, Copy)]
+pub enum Action {
+    Quit,
+    Submit,
+    Reset,
+    ClearTextBox,
+    Undo,
+    Redo,
+    HistoryPrev,
+    HistoryNext,
+    ScrollUp,
+    ScrollDown,
+    Reload,
+    ToggleLog,
+    Paste,
+    Copy,
+    DismissError,
+    Help,
+    Complete,
+    ToggleTape,
+    SaveTape,
+    StackEdit,
+    Exponent,
+    Inspect,
+    CursorLeft,
+    CursorRight,
+    CursorHome,
+    CursorEnd,
+    DeleteForward,
+    NextWorkspace,
+    PopToEntry,
+}
+
+pub type Keybinds = HashMap<(KeyCode, KeyModifiers), Action>;
+
+impl Action {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "quit" => Self::Quit,
+            "submit" => Self::Submit,
+            "reset" => Self::Reset,
+            "clear_text_box" => Self::ClearTextBox,
+            "undo" => Self::Undo,
+            "redo" => Self::Redo,
+            "history_prev" => Self::HistoryPrev,
+            "history_next" => Self::HistoryNext,
+            "scroll_up" => Self::ScrollUp,
+            "scroll_down" => Self::ScrollDown,
+            "reload" => Self::Reload,
+            "toggle_log" => Self::ToggleLog,
+            "paste" => Self::Paste,
+            "copy" => Self::Copy,
+            "dismiss_error" => Self::DismissError,
+            "help" => Self::Help,
+            "complete" => Self::Complete,
+            "toggle_tape" => Self::ToggleTape,
+            "save_tape" => Self::SaveTape,
+            "stack_edit" => Self::StackEdit,
+            "exponent" => Self::Exponent,
+            "inspect" => Self::Inspect,
+            "cursor_left" => Self::CursorLeft,
+            "cursor_right" => Self::CursorRight,
+            "cursor_home" => Self::CursorHome,
+            "cursor_end" => Self::CursorEnd,
+            "delete_forward" => Self::DeleteForward,
+            "next_workspace" => Self::NextWorkspace,
+            "pop_to_entry" => Self::PopToEntry,
+            _ => return None,
+        })
+    }
+
+    pub fn event(self) -> Event {
+        match self {
+            Self::Quit => Event::Quit,
+            Self::Submit => Event::Submit,
+            Self::Reset => Event::Reset,
+            Self::ClearTextBox => Event::ClearTextBox,
+            Self::Undo => Event::Undo,
+            Self::Redo => Event::Redo,
+            Self::HistoryPrev => Event::HistoryPrev,
+            Self::HistoryNext => Event::HistoryNext,
+            Self::ScrollUp => Event::ScrollUp,
+            Self::ScrollDown => Event::ScrollDown,
+            Self::Reload => Event::Reload,
+            Self::ToggleLog => Event::ToggleLog,
+            Self::Paste => Event::Paste,
+            Self::Copy => Event::Copy,
+            Self::DismissError => Event::PopError,
+            Self::Help => Event::Help,
+            Self::Complete => Event::Complete,
+            Self::ToggleTape => Event::ToggleTape,
+            Self::SaveTape => Event::SaveTape,
+            Self::StackEdit => Event::ToggleStackEdit,
+            Self::Exponent => Event::Exponent,
+            Self::Inspect => Event::Inspect,
+            Self::CursorLeft => Event::MoveCursor(CursorMove::Left),
+            Self::CursorRight => Event::MoveCursor(CursorMove::Right),
+            Self::CursorHome => Event::MoveCursor(CursorMove::Home),
+            Self::CursorEnd => Event::MoveCursor(CursorMove::End),
+            Self::DeleteForward => Event::DeleteForward,
+            Self::NextWorkspace => Event::NextWorkspace,
+            Self::PopToEntry => Event::PopToEntry,
+        }
+    }
+}
+
+pub fn defaults() -> Keybinds {
+    let ctrl = |c| (KeyCode::Char(c), KeyModifiers::CONTROL);
+    let plain = |code| (code, KeyModifiers::NONE);
+    let alt = |c| (KeyCode::Char(c), KeyModifiers::ALT);
+    HashMap::from([
+        (ctrl('d'), Action::Quit),
+        (ctrl('w'), Action::ClearTextBox),
+        (ctrl('l'), Action::Reset),
+        (ctrl('z'), Action::Undo),
+        (ctrl('y'), Action::Redo),
+        (ctrl('r'), Action::Reload),
+        (ctrl('e'), Action::ToggleLog),
+        (ctrl('v'), Action::Paste),
+        (ctrl('c'), Action::Copy),
+        (ctrl('t'), Action::ToggleTape),
+        (ctrl('s'), Action::SaveTape),
+        (alt('e'), Action::Exponent),
+        ((KeyCode::Backspace, KeyModifiers::ALT), Action::PopToEntry),
+        (plain(KeyCode::Esc), Action::DismissError),
+        (plain(KeyCode::F(1)), Action::Help),
+        (plain(KeyCode::F(2)), Action::StackEdit),
+        (plain(KeyCode::F(3)), Action::Inspect),
+        (plain(KeyCode::F(4)), Action::NextWorkspace),
+        (plain(KeyCode::Tab), Action::Complete),
+        (plain(KeyCode::Up), Action::HistoryPrev),
+        (plain(KeyCode::Down), Action::HistoryNext),
+        (plain(KeyCode::Left), Action::CursorLeft),
+        (plain(KeyCode::Right), Action::CursorRight),
+        (plain(KeyCode::Home), Action::CursorHome),
+        (plain(KeyCode::End), Action::CursorEnd),
+        (plain(KeyCode::Delete), Action::DeleteForward),
+        (plain(KeyCode::PageUp), Action::ScrollUp),
+        (plain(KeyCode::PageDown), Action::ScrollDown),
+        (plain(KeyCode::Enter), Action::Submit),
+    ])
+}
+
+// keys are written like `ctrl+d`, `alt+shift+f1`, or `pageup`
+fn parse_key(text: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let mut parts: Vec<&str> = text.split('+').collect();
+    // `ctrl++` binds the plus key
+    if text.ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let key = parts.pop()?;
+    let mut modifiers = KeyModifiers::NONE;
+    for modifier in parts {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+    }
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "esc" => KeyCode::Esc,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            f => KeyCode::F(f.strip_prefix('f')?.parse().ok()?),
+        },
+    };
+    Some((code, modifiers))
+}
+
+// the file only overrides the keys it mentions; everything else keeps its default
+pub fn load(path: impl AsRef<Path>) -> (Keybinds, Vec<String>) {
+    let mut keybinds = defaults();
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return (keybinds, vec![]),
+        Err(e) => return (keybinds, vec![format!("Unable to read keybinds.toml: {e}")]),
+    };
+    let table: HashMap<String, String> = match toml::from_str(&text) {
+        Ok(table) => table,
+        Err(e) => return (keybinds, vec![format!("Unable to parse keybinds.toml: {e}")]),
+    };
+    let mut errors = vec![];
+    for (key, action) in table {
+        match (parse_key(&key), Action::from_name(&action)) {
+            (Some(key), Some(action)) => { keybinds.insert(key, action); },
+            (None, _) => errors.push(format!("Unrecognized key `{key}` in keybinds.toml")),
+            (_, None) => errors.push(format!("Unrecognized action `{action}` in keybinds.toml")),
+        }
+    }
+    (keybinds, errors)
+}
+
+pub fn lookup(keybinds: &Keybinds, key: KeyEvent) -> Option<Action> {
+    keybinds.get(&(key.code, key.modifiers)).copied().or_else(|| {
+        // terminals disagree on whether shifted characters like `?` report shift
+        match key.code {
+            KeyCode::Char(_) => keybinds.get(&(key.code, key.modifiers - KeyModifiers::SHIFT)).copied(),
+            // a modifier nothing is bound to, like shift+enter, falls back to the key on its own
+            _ => keybinds.get(&(key.code, KeyModifiers::NONE)).copied(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{defaults, lookup, Action};
+    use crate::Event;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    // an exact binding wins, and a modifier with nothing bound to it falls back to the plain key
+    #[test]
+    fn keys_fall_back() {
+        let keybinds = defaults();
+        let action = |code, modifiers| lookup(&keybinds, KeyEvent::new(code, modifiers)).map(Action::event);
+        assert!(matches!(action(KeyCode::Enter, KeyModifiers::SHIFT), Some(Event::Submit)));
+        assert!(matches!(action(KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::ALT), Some(Event::Submit)));
+        assert!(matches!(action(KeyCode::Backspace, KeyModifiers::ALT), Some(Event::PopToEntry)));
+        assert!(action(KeyCode::Char('q'), KeyModifiers::CONTROL).is_none());
+    }
+}
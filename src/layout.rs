@@ -0,0 +1,106 @@
+use ratatui::layout::Rect;
+use serde::Deserialize;
+
+// the `[layout]` table in config.toml
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    pub error_panel: ErrorPanel,
+    // the share of the stack pane's width in the corner, or of its height at the bottom
+    pub error_panel_size: f64,
+}
+
+// where errors are shown over the stack pane
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorPanel {
+    // the right side, from the top down
+    #[default]
+    Corner,
+    // the full width, along the bottom
+    Bottom,
+    // only in the log
+    Hidden,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            error_panel: ErrorPanel::default(),
+            error_panel_size: 1.0 / 3.0,
+        }
+    }
+}
+
+impl LayoutConfig {
+    // the area errors go in, inside the stack pane's border. hidden errors still get the corner, since
+    // startup problems are shown there either way. None if the pane is too small to have an inside
+    pub fn error_area(&self, pane: Rect) -> Option<Rect> {
+        let inner = Rect {
+            x: pane.x + 1,
+            y: pane.y + 1,
+            width: pane.width.saturating_sub(2),
+            height: pane.height.saturating_sub(2),
+        };
+        if inner.area() == 0 {
+            return None;
+        }
+        // NaN fails the clamp, so it counts as the default
+        let size = if self.error_panel_size.is_nan() { 1.0 / 3.0 } else { self.error_panel_size.clamp(0.1, 1.0) };
+        let share = |length: u16| ((length as f64 * size).round() as u16).clamp(1, length);
+        Some(match self.error_panel {
+            ErrorPanel::Corner | ErrorPanel::Hidden => {
+                let width = share(inner.width);
+                Rect { x: inner.right() - width, width, ..inner }
+            },
+            ErrorPanel::Bottom => {
+                let height = share(inner.height);
+                Rect { y: inner.bottom() - height, height, ..inner }
+            },
+        })
+    }
+}
+
+// where the scrollbar's thumb goes along the right border of a pane showing `visible` of `total` rows, scrolled
+// down `offset` rows: its first row and its length, both counted inside the border. None if everything fits
+pub fn scrollbar_thumb(total: usize, visible: u16, offset: u16) -> Option<(u16, u16)> {
+    let visible_rows = visible as usize;
+    if visible == 0 || total <= visible_rows {
+        return None;
+    }
+    let length = (visible_rows * visible_rows / total).max(1) as u16;
+    let max_scroll = total - visible_rows;
+    let start = (offset as usize).min(max_scroll) * (visible - length) as usize / max_scroll;
+    Some((start as u16, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scrollbar_thumb, ErrorPanel, LayoutConfig};
+    use ratatui::layout::Rect;
+
+    // the error panel fits inside the stack pane's border wherever it's anchored, and is skipped when there's no inside
+    #[test]
+    fn error_panel_fits() {
+        let pane = Rect::new(0, 0, 32, 14);
+        let mut layout = LayoutConfig::default();
+        assert_eq!(layout.error_area(pane), Some(Rect::new(21, 1, 10, 12)));
+        layout.error_panel = ErrorPanel::Bottom;
+        layout.error_panel_size = 0.5;
+        assert_eq!(layout.error_area(pane), Some(Rect::new(1, 7, 30, 6)));
+        layout.error_panel_size = f64::NAN;
+        assert_eq!(layout.error_area(pane), Some(Rect::new(1, 9, 30, 4)));
+        for pane in [Rect::new(0, 0, 2, 10), Rect::new(0, 0, 10, 1), Rect::default()] {
+            assert_eq!(layout.error_area(pane), None);
+        }
+    }
+
+    #[test]
+    fn scrollbar_thumb_placed() {
+        assert_eq!(scrollbar_thumb(5, 5, 0), None);
+        assert_eq!(scrollbar_thumb(10, 5, 0), Some((0, 2)));
+        assert_eq!(scrollbar_thumb(10, 5, 5), Some((3, 2)));
+        assert_eq!(scrollbar_thumb(1000, 5, 995), Some((4, 1)));
+        assert_eq!(scrollbar_thumb(10, 0, 0), None);
+    }
+}
@@ -1,31 +1,223 @@
 // #![deny(elided_lifetimes_in_paths)]
+mod angle;
+mod clipboard;
+mod error;
+mod format;
+mod keybinds;
+mod layout;
+mod number;
+mod paths;
+mod persist;
+mod selftest;
+mod settings;
+#[cfg(test)]
+mod tests;
+mod theme;
+mod unit;
+mod value;
+
 use crossterm::{
+    cursor::{self, MoveTo, Show},
     execute, 
-    terminal::{enable_raw_mode, disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-    event,
-    event::{Event as CEvent, KeyEvent, KeyCode, KeyModifiers},
+    terminal::{self, enable_raw_mode, disable_raw_mode, is_raw_mode_enabled, ClearType, EnterAlternateScreen, LeaveAlternateScreen, ScrollUp},
+    event::{self, EnableBracketedPaste, DisableBracketedPaste, EnableMouseCapture, DisableMouseCapture},
+    event::{Event as CEvent, KeyEvent, KeyCode, MouseButton, MouseEventKind},
 };
-use directories::ProjectDirs;
-use mlua::{AsChunk, Lua, Table, Variadic};
+use mlua::{AsChunk, HookTriggers, Lua, Table, Variadic};
 use uiua::{Uiua, UiuaResult};
+use angle::AngleMode;
+use error::OpError;
+use format::{group_digits, Base, DisplayFormat};
+use number::{round_to_places, round_to_significant, Number, Rational};
+use unit::Unit;
+use value::Value;
+use keybinds::Action;
+use layout::{scrollbar_thumb, ErrorPanel};
+use paths::Paths;
+use persist::{SavedWorkspace, Session};
+use settings::{EmptyEnterAction, EmptyTotal, GrowthDirection, OverflowPolicy, Settings, StackLabels};
+use theme::Theme;
 
 use std::{
-    collections::{HashMap, VecDeque}, error::Error, io, mem, sync::mpsc::{self, Sender}, thread, time::{Duration, Instant}
+    cell::{Cell, RefCell}, collections::{HashMap, HashSet, VecDeque}, error::Error, fs, io::{self, Write}, mem, path::{Path, PathBuf}, rc::Rc, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, Sender}, Arc, Mutex, PoisonError}, thread, time::{Duration, Instant, SystemTime}
 };
 
 use ratatui::{
-    backend::CrosstermBackend, layout::Rect, text::{Span, Spans}, widgets::{Block, BorderType, Borders, Paragraph, Wrap}, Terminal
+    backend::{Backend, CrosstermBackend}, layout::{Alignment, Rect}, style::{Modifier, Style}, text::{Span, Spans}, widgets::{Clear, Paragraph, Wrap}, Frame, Terminal, TerminalOptions, Viewport
 };
 
 struct Calculator {
-    stack: Vec<f64>, // TODO: change from f64 to precise value
+    stack: Vec<Value>,
     text_box: String,
     previous: String,
     operations: HashMap<String, Operation>,
     uiua: Uiua,
     lua: Lua,
-    errors: VecDeque<String>,
-    no_lua_loaded: bool,
+    // oldest first, each with when it disappears. they all share one timeout, so they expire in order
+    errors: VecDeque<(OpError, Option<Instant>)>,
+    // problems loading configs, shown together until dismissed so none are missed
+    startup_errors: Vec<String>,
+    // None keeps errors until they're dismissed
+    error_timeout: Option<Duration>,
+    // how long a Lua or Uiua function may run before it's stopped
+    operation_timeout: Option<Duration>,
+    // each entry that changed the stack, with the value it left on top
+    tape: Vec<String>,
+    show_tape: bool,
+    // every entry this session, as typed, for exportscript. script_stack is what replaying it leaves
+    script: Vec<String>,
+    script_stack: Vec<Value>,
+    // how many times each operation has been run from an entry, across sessions
+    usage: HashMap<String, u64>,
+    // the text of the last entry that was just a number, the previous operation before it, and the stack depth it
+    // left. only good while that value is still on top
+    last_push: Option<(String, String, usize)>,
+    // the name of the workspace in use, whose stack is `stack`, and every other one by name
+    workspace: String,
+    workspaces: HashMap<String, Workspace>,
+    // every error this session, kept after it leaves the corner box
+    log: Vec<(Instant, String)>,
+    show_log: bool,
+    started: Instant,
+    settings: Settings,
+    theme: Theme,
+    data_dir: Option<PathBuf>,
+    // defining text of Lua and Uiua operations, captured as they're loaded
+    sources: HashMap<String, String>,
+    overlay: Option<Overlay>,
+    // oldest snapshot at the front
+    undo: VecDeque<Vec<Value>>,
+    redo: Vec<Vec<Value>>,
+    display_format: DisplayFormat,
+    output_base: Base,
+    registers: HashMap<String, Value>,
+    history: Vec<String>,
+    // equal to history.len() when not browsing
+    history_index: usize,
+    // characters between the cursor and the end of the text box, so 0 follows whatever is typed
+    cursor_back: usize,
+    // rows scrolled away from the newest value; 0 follows new values as they're pushed
+    stack_scroll: u16,
+    angle_mode: AngleMode,
+    // the word Tab is completing and which match it last put in the text box
+    completion: Option<(String, usize)>,
+    // equality_epsilon from the settings, shared with the operations that compare values
+    epsilon: Rc<Cell<f64>>,
+    // empty_total from the settings
+    empty_total: Rc<Cell<EmptyTotal>>,
+    // when reset was first pressed, while it waits to be pressed again
+    armed_reset: Option<Instant>,
+    // the values the last operation took off the stack, oldest first, shared with `lastx`
+    last_x: Rc<RefCell<Vec<Value>>>,
+    // what the `ripen` table in Lua reaches while a Lua function runs
+    lua_shared: Arc<Mutex<LuaShared>>,
+    // set by Esc to stop a Lua function running in the background
+    cancel: Arc<AtomicBool>,
+    // true in the TUI, where a lone Lua or Uiua operation runs on its own thread so the screen keeps drawing
+    background: bool,
+    running: Option<Running>,
+    mode: Mode,
+    // the highlighted value in stack edit mode. back in entry mode, the slot an edited value returns to
+    selected: Option<usize>,
+}
+
+const UNDO_LIMIT: usize = 100;
+// below this the stack pane and the text box don't both fit, so only a message is drawn
+const MIN_WIDTH: u16 = 12;
+const MIN_HEIGHT: u16 = 7;
+// --inline draws in this many rows instead of taking over the screen
+const INLINE_HEIGHT: u16 = 16;
+// operations that keep exact operands exact, unless the result doesn't fit in an i64 fraction
+const EXACT_OPERATIONS: &[&str] = &["+", "-", "*", "/", "abs", "fact", "total", "product"];
+// how far off, relative to the value, the fraction from tofrac may be
+const FRACTION_TOLERANCE: f64 = 1e-6;
+// how long a first reset waits for the second when confirm_reset is on
+const RESET_WINDOW: Duration = Duration::from_secs(3);
+// rows the mouse wheel scrolls per notch
+const WHEEL_LINES: u16 = 3;
+// the most times `N*op` runs an operation. built-ins have no timeout, so a huge count would hang
+const MAX_REPEAT: u64 = 1_000_000;
+
+const BASE_LUA: &str = include_str!("base.lua");
+
+// the stack is lent here for the length of each Lua call, since the functions in the `ripen` table outlive it
+#[derive(Default)]
+struct LuaShared {
+    stack: Vec<Value>,
+    // from ripen.error, shown once the call returns
+    messages: Vec<String>,
+    // false outside of a call, when there's no stack to lend
+    lending: bool,
+}
+
+// a Lua or Uiua call with its inputs copied off the stack, so it can run on another thread while the screen
+// keeps drawing. nothing is taken off the stack until apply_outcome has the result
+struct Call {
+    kind: CallKind,
+    timeout: Option<Duration>,
+    angle_mode: AngleMode,
+    digits: u32,
+}
+
+enum CallKind {
+    // `under` is lent to the ripen table. a whole-stack function gets `args` as one table, with nothing under it
+    Lua { function: String, args: Vec<f64>, under: Vec<Value>, whole_stack: bool },
+    Uiua { function: uiua::Function, args: Vec<uiua::Value> },
+}
+
+// what a call gave back, before it's put on the stack
+enum Outcome {
+    // what the function returned, what's left of the lent stack, and anything from ripen.error
+    Lua { out: Result<Vec<f64>, String>, under: Vec<Value>, messages: Vec<String>, whole_stack: bool },
+    Uiua { out: Result<Vec<Value>, String>, args: usize, outputs: usize },
+}
+
+// a call that ran on its own thread, with the interpreters it took along
+struct Finished {
+    lua: Lua,
+    uiua: Uiua,
+    outcome: Outcome,
+}
+
+// an entry whose call is running on its own thread. the rest of run_entry happens once it's done
+struct Running {
+    // as typed, for the tape and usage, and the operation it resolved to
+    entry: String,
+    key: String,
+    before: Vec<Value>,
+    // what goes in last_x if it works
+    operands: Vec<Value>,
+}
+
+// in stack edit mode, up and down select a value, Delete drops it, and Enter takes it out to edit
+#[derive(Clone, Copy, PartialEq, Default)]
+enum Mode {
+    #[default]
+    Entry,
+    StackEdit,
+}
+
+// a stack and everything that goes with it, put aside while another workspace is in use
+#[derive(Default)]
+struct Workspace {
+    stack: Vec<Value>,
+    history: Vec<String>,
+    previous: String,
+    undo: VecDeque<Vec<Value>>,
+    redo: Vec<Vec<Value>>,
+    // whatever was typed but not entered
+    text_box: String,
+    last_x: Vec<Value>,
+}
+
+const DEFAULT_WORKSPACE: &str = "main";
+
+// covers the stack until any key is pressed
+struct Overlay {
+    title: String,
+    lines: Vec<String>,
+    // moved with PageUp and PageDown
+    scroll: u16,
 }
 
 enum Event {
@@ -35,316 +227,3424 @@ enum Event {
     Quit,
     Reset,
     ClearTextBox,
-    PushError(String),
+    PushError(OpError),
     PopError,
+    // a call started by submit has finished on its own thread
+    OperationResult(Box<Finished>),
+    ConfigChanged(PathBuf),
+    Undo,
+    Redo,
+    HistoryPrev,
+    HistoryNext,
+    ScrollUp,
+    ScrollDown,
+    Reload,
+    ToggleLog,
+    // Paste reads the clipboard; PasteText is pasted through the terminal
+    Paste,
+    PasteText(String),
+    Copy,
+    Resize,
+    Help,
+    Complete,
+    ToggleTape,
+    SaveTape,
+    ToggleStackEdit,
+    // the EE key, which starts the exponent of the number being typed
+    Exponent,
+    // shows the definition of the operation being typed
+    Inspect,
+    MoveCursor(CursorMove),
+    // deletes the character after the cursor
+    DeleteForward,
+    NextWorkspace,
+    // takes the number just entered back into the text box
+    PopToEntry,
+    // a left click at a column and row of the screen
+    Click(u16, u16),
+    WheelUp,
+    WheelDown,
+}
+
+#[derive(Clone, Copy)]
+enum CursorMove {
+    Left,
+    Right,
+    Home,
+    End,
+}
+
+// operations get the angle mode so trig can follow it, and so `drg` can change it, and the output base
+// so digits can split in it. on failure the stack is left as it was and the error is shown
+type RustFn = dyn Fn(&mut Vec<Value>, &mut AngleMode, Base) -> Result<(), String>;
+
+// an entry that changes the calculator rather than just the stack, so it can't be an operation.
+// dispatch looks commands up before operations, so they run anywhere an operation can: on a line
+// like `4 2 fmt`, in macros and aliases, and in repeats
+struct Command {
+    name: &'static str,
+    // shown in help and in the error when the command is misused
+    usage: &'static str,
+    about: &'static str,
+    // whether it takes an argument after a colon, like `stats:5`
+    argument: bool,
+    run: CommandFn,
+}
+
+// the calculator, the argument, and where to send notices like `Copied 3 values`
+type CommandFn = fn(&mut Calculator, Option<&str>, &Sender<Event>) -> Result<(), String>;
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "fmt",
+        usage: "<selector> fmt, or <digits> <selector> fmt",
+        about: "Pops a display format: 0 auto, 1 fixed, 2 scientific, 3 engineering",
+        argument: false,
+        run: |c, _, _| c.set_format_from_stack(),
+    },
+    Command {
+        name: "base",
+        usage: "base",
+        about: "Cycles the base integers are shown in: decimal, hexadecimal, octal, binary",
+        argument: false,
+        run: |c, _, _| {
+            c.output_base = c.output_base.next();
+            Ok(())
+        },
+    },
+    Command {
+        name: "copyall",
+        usage: "copyall",
+        about: "Copies the whole stack, one value per line with the newest last",
+        argument: false,
+        run: |c, _, tx| {
+            let count = c.copy_stack()?;
+            // unwrap safety: rx lasts program lifetime
+            tx.send(Event::PushError(OpError::Notice(format!("Copied {count} values")))).unwrap();
+            Ok(())
+        },
+    },
+    Command {
+        name: "exportlua",
+        usage: "exportlua",
+        about: "Writes the stack and registers to session.lua in the data directory",
+        argument: false,
+        run: |c, _, tx| {
+            let path = c.export_lua()?;
+            // unwrap safety: rx lasts program lifetime
+            tx.send(Event::PushError(OpError::Notice(format!("Exported session to {}", path.display())))).unwrap();
+            Ok(())
+        },
+    },
+    Command {
+        name: "importlua",
+        usage: "importlua",
+        about: "Replaces the stack and registers with the ones in session.lua",
+        argument: false,
+        run: |c, _, _| c.import_lua(),
+    },
+    Command {
+        name: "stats",
+        usage: "stats, or stats:<count>",
+        about: "Lists the most used operations, 10 unless a count is given",
+        argument: true,
+        run: |c, count, _| {
+            let count = count.map_or(Ok(10), str::parse::<usize>).map_err(|_| "Expected `stats` or `stats:<count>`")?;
+            c.overlay = Some(c.usage_stats(count));
+            Ok(())
+        },
+    },
+    Command {
+        name: "window",
+        usage: "window:<size>:<operation>",
+        about: "Replaces the stack with the operation applied to every run of size consecutive values",
+        argument: true,
+        run: |c, argument, tx| {
+            let usage = "Expected `window:<size>:<operation>`";
+            let (size, op) = argument.and_then(|a| a.split_once(':')).filter(|(_, op)| !op.is_empty()).ok_or(usage)?;
+            let size = size.parse::<usize>().map_err(|_| format!("`{size}` is not a valid window size"))?;
+            c.window(size, op, tx.clone())
+        },
+    },
+    // shows the fraction without storing it, so the float on the stack keeps its full precision
+    Command {
+        name: "tofrac",
+        usage: "tofrac",
+        about: "Shows the top value as the closest fraction with a denominator up to max_denominator",
+        argument: false,
+        run: |c, _, tx| {
+            let top = c.stack.last().ok_or("Nothing to show as a fraction")?;
+            let fraction = match top {
+                Value::Scalar(Number::Rational(r)) => *r,
+                Value::Scalar(Number::Float(n)) => closest_fraction(*n, c.settings.max_denominator.max(1))?,
+                _ => return Err("Expected a real number".into()),
+            };
+            // unwrap safety: rx lasts program lifetime
+            tx.send(Event::PushError(OpError::Notice(format!("{} ≈ {fraction}", c.format_value(top))))).unwrap();
+            Ok(())
+        },
+    },
+    // a float, unlike typing `3/4`, which is exact
+    Command {
+        name: "fromfrac",
+        usage: "fromfrac:<a>/<b>",
+        about: "Pushes the fraction a/b as a float",
+        argument: true,
+        run: |c, fraction, _| {
+            let usage = "Expected `fromfrac:<a>/<b>`";
+            let (a, b) = fraction.and_then(|f| f.split_once('/')).ok_or(usage)?;
+            let (a, b) = a.parse::<f64>().ok().zip(b.parse::<f64>().ok()).ok_or(usage)?;
+            if b == 0.0 {
+                return Err("Division by zero".into());
+            }
+            c.stack.push(Value::from(a / b));
+            Ok(())
+        },
+    },
+];
+
+// how many values a built-in operation takes off the stack
+#[derive(Clone, Copy)]
+enum Arity {
+    Fixed(usize),
+    // a count on top, and that many values under it
+    Counted,
+    // everything, like total and sort
+    Stack,
 }
 
 enum Operation {
-    Rust(Box<dyn Fn(&mut Vec<f64>) -> bool>),
+    Rust(Arity, Box<RustFn>),
     Uiua(uiua::Function),
+    // a value bound in the Uiua config, pushed as it is
+    UiuaConst(Value),
     Lua(String, usize),
+    // takes the whole stack as a table and returns the table that replaces it
+    LuaStack(String),
+    Macro(Vec<String>),
+    // another name for the operation it holds, from aliases.toml
+    Alias(String),
 }
 
 impl Calculator {
     fn new() -> Self {
+        let epsilon = Rc::new(Cell::new(0.0));
+        let empty_total = Rc::new(Cell::new(EmptyTotal::default()));
+        let last_x: Rc<RefCell<Vec<Value>>> = Rc::default();
         Self {
             stack: vec![],
             text_box: "".into(),
             previous: "".into(),
             operations: {
                 let mut map = HashMap::new();
-                map.insert("+".into(), Operation::new_rust(|&[a, b]| vec![a + b]));
-                map.insert("-".into(), Operation::new_rust(|&[a, b]| vec![a - b]));
-                map.insert("*".into(), Operation::new_rust(|&[a, b]| vec![a * b]));
-                map.insert("/".into(), Operation::new_rust(|&[a, b]| vec![a / b]));
-                map.insert("^".into(), Operation::new_rust(|&[a, b]| vec![a.powf(b)]));
-                map.insert("sin".into(), Operation::new_rust(|&[a]| vec![a.sin()]));
-                map.insert("cos".into(), Operation::new_rust(|&[a]| vec![a.cos()]));
-                map.insert("tan".into(), Operation::new_rust(|&[a]| vec![a.tan()]));
-                map.insert("asin".into(), Operation::new_rust(|&[a]| vec![a.asin()]));
-                map.insert("acos".into(), Operation::new_rust(|&[a]| vec![a.acos()]));
-                map.insert("atan".into(), Operation::new_rust(|&[a]| vec![a.atan()]));
-                map.insert("ln".into(), Operation::new_rust(|&[a]| vec![a.ln()]));
-                map.insert("swap".into(), Operation::new_rust(|&[a, b]| vec![b, a]));
-                map.insert("sqrt".into(), Operation::new_rust(|&[a]| vec![a.sqrt()]));
+                // exact when both operands are rational
+                // vectors of the same length add elementwise
+                map.insert("+".into(), Operation::new_value(|[a, b]| match (a, b) {
+                    (Value::Vector(a), Value::Vector(b)) if a.len() == b.len() => {
+                        Ok(vec![Value::Vector(a.iter().zip(&b).map(|(x, y)| x + y).collect())])
+                    },
+                    (Value::Vector(_), Value::Vector(_)) => Err("Vectors must be the same length".into()),
+                    (a, b) => combine_units(a, b, Number::add, "add"),
+                }).checked("Result of + is out of range"));
+                map.insert("-".into(), Operation::new_value(|[a, b]| combine_units(a, b, Number::sub, "subtract")).checked("Result of - is out of range"));
+                // a number with a unit can be scaled by a plain number, but units don't multiply together
+                map.insert("*".into(), Operation::new_value(|[a, b]| match (a, b) {
+                    (Value::Scalar(a), Value::Scalar(b)) => Ok(vec![a.mul(b).into()]),
+                    (Value::Quantity(q, unit), Value::Scalar(n)) | (Value::Scalar(n), Value::Quantity(q, unit)) => {
+                        let n = n.as_real().ok_or("Units only go with real numbers")?;
+                        Ok(vec![Value::Quantity(q * n, unit)])
+                    },
+                    (Value::Quantity(..), Value::Quantity(..)) => Err("Can't multiply two numbers with units".into()),
+                    _ => Err("Expected numbers, not vectors".into()),
+                }).checked("Result of * is out of range"));
+                // dividing two lengths, say, gives a plain ratio
+                map.insert("/".into(), Operation::new_value(|[a, b]| match (a, b) {
+                    (Value::Scalar(a), Value::Scalar(b)) => Ok(vec![a.div(b).into()]),
+                    (Value::Quantity(q, unit), Value::Scalar(n)) => {
+                        let n = n.as_real().ok_or("Units only go with real numbers")?;
+                        Ok(vec![Value::Quantity(q / n, unit)])
+                    },
+                    (Value::Quantity(a, unit), Value::Quantity(b, other)) => {
+                        Ok(vec![Value::from(a / other.convert(b, unit)?)])
+                    },
+                    (Value::Scalar(_), Value::Quantity(..)) => Err("Can't divide by a number with a unit".into()),
+                    _ => Err("Expected numbers, not vectors".into()),
+                }).checked("Division by zero"));
+                map.insert("^".into(), Operation::new_number(|&[a, b]| vec![a.pow(b)]).checked("Result of ^ is undefined"));
+                // exact up to 20!, which is the most an i64 holds, then floats up to 170!, the most an f64 holds
+                map.insert("fact".into(), Operation::new_integer(|[n]| match n {
+                    ..0 => Err("Factorial needs a non-negative integer".into()),
+                    0..=20 => Ok(Number::integer((1..=n).product())),
+                    21..=170 => Ok((1..=n).map(|i| i as f64).product::<f64>().into()),
+                    _ => Err("Factorials past 170! are too big for a float".into()),
+                }));
+                map.insert("gcd".into(), Operation::new_integer(|[a, b]| Ok(Number::integer(gcd(a, b)))));
+                map.insert("lcm".into(), Operation::new_integer(|[a, b]| match gcd(a, b) {
+                    0 => Ok(Number::integer(0)),
+                    divisor => (a / divisor).checked_mul(b).map(|n| Number::integer(n.abs())).ok_or("Result of lcm is too big".into()),
+                }));
+                // the remainder is never negative, unlike `fmod` in C: `-7 3 mod` is 2
+                map.insert("mod".into(), Operation::new_rust(|&[a, b]| vec![a.rem_euclid(b)]).checked("Division by zero"));
+                // truncated remainder, which takes the sign of a: `-7 3 %` is -1 where `mod` gives 2
+                map.insert("%".into(), Operation::new_rust(|&[a, b]| vec![a % b]).checked("Division by zero"));
+                // halves round away from zero, so `-2.5 round` is -3
+                map.insert("round".into(), Operation::new_rust(|&[a]| vec![a.round()]));
+                map.insert("floor".into(), Operation::new_rust(|&[a]| vec![a.floor()]));
+                map.insert("ceil".into(), Operation::new_rust(|&[a]| vec![a.ceil()]));
+                map.insert("trunc".into(), Operation::new_rust(|&[a]| vec![a.trunc()]));
+                // `a n roundto` rounds a to n decimal places
+                map.insert("roundto".into(), Operation::new_value(|[a, places]| {
+                    let a = a.as_real().ok_or("Expected a real number")?;
+                    match places.as_real() {
+                        Some(places) if places >= 0.0 && places.fract() == 0.0 => Ok(vec![Value::from(round_to_places(a, places as u32))]),
+                        _ => Err("roundto needs a non-negative integer number of places".into()),
+                    }
+                }));
+                map.insert("and".into(), Operation::new_bitwise(|[a, b]| Some(a & b)));
+                map.insert("or".into(), Operation::new_bitwise(|[a, b]| Some(a | b)));
+                map.insert("xor".into(), Operation::new_bitwise(|[a, b]| Some(a ^ b)));
+                map.insert("not".into(), Operation::new_bitwise(|[a]| Some(!a)));
+                map.insert("shl".into(), Operation::new_bitwise(|[a, b]| a.checked_shl(b.try_into().ok()?)));
+                // keeps the sign, so `-8 1 shr` is -4
+                map.insert("shr".into(), Operation::new_bitwise(|[a, b]| a.checked_shr(b.try_into().ok()?)));
+                // trig takes angles in the current angle mode, and inverse trig gives them back in it
+                map.insert("sin".into(), Operation::new_angle(|mode, &[a]| vec![mode.to_radians(a).sin()]).checked("sin needs a finite angle"));
+                map.insert("cos".into(), Operation::new_angle(|mode, &[a]| vec![mode.to_radians(a).cos()]).checked("cos needs a finite angle"));
+                // the float nearest a right angle in radians has a tan around 1e16 rather than infinity, so poles are found first
+                map.insert("tan".into(), Operation::new_angle(|mode, &[a]| {
+                    vec![if mode.is_odd_right_angle(a) { f64::NAN } else { mode.to_radians(a).tan() }]
+                }).checked("tan is undefined at odd multiples of a right angle"));
+                map.insert("asin".into(), Operation::new_angle(|mode, &[a]| vec![mode.convert_from_radians(a.asin())]).checked("asin needs a value from -1 to 1"));
+                map.insert("acos".into(), Operation::new_angle(|mode, &[a]| vec![mode.convert_from_radians(a.acos())]).checked("acos needs a value from -1 to 1"));
+                map.insert("atan".into(), Operation::new_angle(|mode, &[a]| vec![mode.convert_from_radians(a.atan())]));
+                // cycles radians, degrees, gradians
+                map.insert("drg".into(), Operation::Rust(Arity::Fixed(0), Box::new(|_, mode, _| {
+                    *mode = mode.next();
+                    Ok(())
+                })));
+                map.insert("ln".into(), Operation::new_rust(|&[a]| vec![a.ln()]).checked("ln needs a positive number"));
+                map.insert("swap".into(), Operation::new_value(|[a, b]| Ok(vec![b, a])));
+                map.insert("dup".into(), Operation::new_value(|[a]| Ok(vec![a.clone(), a])));
+                map.insert("drop".into(), Operation::new_value(|[_]| Ok(vec![])));
+                map.insert("over".into(), Operation::new_value(|[a, b]| Ok(vec![a.clone(), b, a])));
+                // the third value from the top moves to the top
+                map.insert("rot".into(), Operation::new_value(|[a, b, c]| Ok(vec![b, c, a])));
+                map.insert("clear".into(), Operation::new_stack(Vec::clear));
+                map.insert("reverse".into(), Operation::new_stack(|v| v.reverse()));
+                // values within equality_epsilon of each other count as duplicates. the first one is kept
+                let uniq_epsilon = epsilon.clone();
+                map.insert("uniq".into(), Operation::new_stack(move |v| v.dedup_by(|b, a| a.approx_eq(b, uniq_epsilon.get()))));
+                let dedup_epsilon = epsilon.clone();
+                map.insert("dedup".into(), Operation::new_stack(move |v| {
+                    let mut kept: Vec<Value> = Vec::with_capacity(v.len());
+                    for value in v.drain(..) {
+                        if !kept.iter().any(|k| k.approx_eq(&value, dedup_epsilon.get())) {
+                            kept.push(value);
+                        }
+                    }
+                    *v = kept;
+                }));
+                // newest on top, so `sort` leaves the largest value on top
+                map.insert("sort".into(), Operation::new_sort(f64::total_cmp));
+                map.insert("rsort".into(), Operation::new_sort(|a, b| b.total_cmp(a)));
+                map.insert("sqrt".into(), Operation::new_number(|&[a]| vec![a.sqrt()]));
+                map.insert("abs".into(), Operation::new_number(|&[a]| vec![a.abs()]));
                 map.insert("cbrt".into(), Operation::new_rust(|&[a]| vec![a.cbrt()]));
+                // `a pct` is a/100, `a b pctof` is b% of a, and `old new pctchg` is the percent change from old to new
+                map.insert("pct".into(), Operation::new_rust(|&[a]| vec![a / 100.0]));
+                map.insert("pctof".into(), Operation::new_rust(|&[a, b]| vec![a * b / 100.0]).checked("Result of pctof is out of range"));
+                map.insert("pctchg".into(), Operation::new_rust(|&[old, new]| vec![(new - old) / old * 100.0]).checked("pctchg needs a nonzero starting value"));
+                // constants each push a single value
+                // mathematical
                 map.insert("pi".into(), Operation::new_rust(|&[]| vec![std::f64::consts::PI]));
+                map.insert("e".into(), Operation::new_rust(|&[]| vec![std::f64::consts::E]));
+                map.insert("tau".into(), Operation::new_rust(|&[]| vec![std::f64::consts::TAU]));
+                map.insert("phi".into(), Operation::new_rust(|&[]| vec![1.618_033_988_749_895]));
+                map.insert("sqrt2".into(), Operation::new_rust(|&[]| vec![std::f64::consts::SQRT_2]));
+                // physical, in SI units
+                map.insert("c".into(), Operation::new_rust(|&[]| vec![299_792_458.0]));
+                map.insert("na".into(), Operation::new_rust(|&[]| vec![6.022_140_76e23]));
+                map.insert("h".into(), Operation::new_rust(|&[]| vec![6.626_070_15e-34]));
+                // standard gravity, not the gravitational constant
+                map.insert("g".into(), Operation::new_rust(|&[]| vec![9.806_65]));
+                // in the output base
+                map.insert("digits".into(), Operation::Rust(Arity::Fixed(1), Box::new(|v, _, base| digits(v, base.radix()))));
+                map.insert("undigits".into(), Operation::Rust(Arity::Fixed(1), Box::new(|v, _, base| undigits(v, base.radix()))));
+                map.insert("sum".into(), Operation::new_vector(|v| Ok(v.iter().sum::<f64>().into())));
+                map.insert("mean".into(), Operation::new_vector(|v| match v.len() {
+                    0 => Err("Can't take the mean of an empty vector".into()),
+                    len => Ok((v.iter().sum::<f64>() / len as f64).into()),
+                }));
+                map.insert("len".into(), Operation::new_vector(|v| Ok(Number::integer(v.len() as i64))));
+                // pop a count n, then reduce the n values under it
+                map.insert("sumn".into(), Operation::new_counted(|v| Ok(v.iter().sum::<f64>().into())));
+                map.insert("prodn".into(), Operation::new_counted(|v| Ok(v.iter().product::<f64>().into())));
+                // the whole stack down to one value. sums and products of fractions stay exact
+                map.insert("total".into(), Operation::new_total(Some(Number::integer(0)), empty_total.clone(), |v| Ok(v.iter().fold(Number::integer(0), |a, &b| a.add(b)))));
+                map.insert("product".into(), Operation::new_total(Some(Number::integer(1)), empty_total.clone(), |v| Ok(v.iter().fold(Number::integer(1), |a, &b| a.mul(b)))));
+                map.insert("stackmax".into(), Operation::new_total(None, empty_total.clone(), |v| stack_extreme(v, f64::total_cmp)));
+                map.insert("stackmin".into(), Operation::new_total(None, empty_total.clone(), |v| stack_extreme(v, |a, b| b.total_cmp(a))));
+                map.insert("m2ft".into(), Operation::new_conversion(Unit::Meter, Unit::Foot));
+                map.insert("ft2m".into(), Operation::new_conversion(Unit::Foot, Unit::Meter));
+                map.insert("cm2in".into(), Operation::new_conversion(Unit::Centimeter, Unit::Inch));
+                map.insert("in2cm".into(), Operation::new_conversion(Unit::Inch, Unit::Centimeter));
+                map.insert("km2mi".into(), Operation::new_conversion(Unit::Kilometer, Unit::Mile));
+                map.insert("mi2km".into(), Operation::new_conversion(Unit::Mile, Unit::Kilometer));
+                map.insert("kg2lb".into(), Operation::new_conversion(Unit::Kilogram, Unit::Pound));
+                map.insert("lb2kg".into(), Operation::new_conversion(Unit::Pound, Unit::Kilogram));
+                map.insert("g2oz".into(), Operation::new_conversion(Unit::Gram, Unit::Ounce));
+                map.insert("oz2g".into(), Operation::new_conversion(Unit::Ounce, Unit::Gram));
+                map.insert("c2f".into(), Operation::new_conversion(Unit::Celsius, Unit::Fahrenheit));
+                map.insert("f2c".into(), Operation::new_conversion(Unit::Fahrenheit, Unit::Celsius));
+                map.insert("c2k".into(), Operation::new_conversion(Unit::Celsius, Unit::Kelvin));
+                map.insert("k2c".into(), Operation::new_conversion(Unit::Kelvin, Unit::Celsius));
+                map.insert("nounit".into(), Operation::new_value(|[a]| match a {
+                    Value::Quantity(n, _) => Ok(vec![Value::from(n)]),
+                    a => Ok(vec![a]),
+                }));
+                // puts back what the last operation took off the stack, in the same order
+                let restore = last_x.clone();
+                map.insert("lastx".into(), Operation::Rust(Arity::Fixed(0), Box::new(move |v, _, _| {
+                    let restore = restore.borrow();
+                    if restore.is_empty() {
+                        return Err("No operation has taken values off the stack yet".into());
+                    }
+                    v.extend(restore.iter().cloned());
+                    Ok(())
+                })));
+                map.insert("packn".into(), Operation::new_counted(|v| Ok(Value::Vector(v.to_vec()))));
+                map.insert("dupn".into(), Operation::new_counted_values(|v| Ok(v.iter().chain(v).cloned().collect())));
+                // how many values there were before it, so `depth sumn` adds up everything
+                map.insert("depth".into(), Operation::Rust(Arity::Fixed(0), Box::new(|v, _, _| {
+                    v.push(Number::integer(v.len() as i64).into());
+                    Ok(())
+                })));
+                // depths count from 0 at the top, like Forth: `0 pick` is dup and `2 roll` is rot
+                map.insert("pick".into(), Operation::new_depth(|v, depth| v.push(v[v.len() - 1 - depth].clone())));
+                map.insert("roll".into(), Operation::new_depth(|v, depth| {
+                    let value = v.remove(v.len() - 1 - depth);
+                    v.push(value);
+                }));
+                map.insert("sumrange".into(), Operation::new_range(|range| range.sum()));
+                map.insert("prodrange".into(), Operation::new_range(|range| range.product()));
+                // pops n, then n y values, then n x values. pushes the slope, then the intercept
+                // x and y as two vectors, x pushed first. r² is only pushed by linregr
+                map.insert("linreg".into(), Operation::new_value(|[x, y]| {
+                    let (slope, intercept, _) = linear_fit(x, y)?;
+                    Ok(vec![slope.into(), intercept.into()])
+                }));
+                map.insert("linregr".into(), Operation::new_value(|[x, y]| {
+                    let (slope, intercept, r2) = linear_fit(x, y)?;
+                    let r2 = r2.ok_or("r² is undefined when every y is the same")?;
+                    Ok(vec![slope.into(), intercept.into(), r2.into()])
+                }));
                 map
             },
-            uiua: Uiua::with_safe_sys(),
+            uiua: Self::fresh_uiua(Some(Duration::from_secs(5))),
             lua: Lua::new(),
             errors: VecDeque::new(),
-            no_lua_loaded: true,
+            startup_errors: Vec::new(),
+            error_timeout: Some(Duration::from_secs(4)),
+            operation_timeout: Some(Duration::from_secs(5)),
+            tape: Vec::new(),
+            script: Vec::new(),
+            script_stack: Vec::new(),
+            usage: HashMap::new(),
+            last_push: None,
+            workspace: DEFAULT_WORKSPACE.into(),
+            workspaces: HashMap::new(),
+            show_tape: false,
+            log: Vec::new(),
+            show_log: false,
+            started: Instant::now(),
+            settings: Settings::default(),
+            theme: Theme::default(),
+            data_dir: None,
+            sources: HashMap::new(),
+            overlay: None,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            display_format: DisplayFormat::Auto,
+            output_base: Base::Decimal,
+            registers: HashMap::new(),
+            history: Vec::new(),
+            history_index: 0,
+            cursor_back: 0,
+            stack_scroll: 0,
+            angle_mode: AngleMode::default(),
+            completion: None,
+            epsilon,
+            empty_total,
+            last_x,
+            armed_reset: None,
+            lua_shared: Arc::default(),
+            cancel: Arc::default(),
+            background: false,
+            running: None,
+            mode: Mode::default(),
+            selected: None,
         }
     }
     // returns false if unsuccessful. mutates stack and returns true if successful.
+    // an operation that takes values off the stack leaves them in last_x; one that only pushes leaves it alone.
+    // macros and aliases come back through here for each operation they run, so they leave what the last one took
     fn operate(&mut self, text: String, tx: Sender<Event>) -> bool {
+        let name = text.to_lowercase();
+        let taken = self.operations.get(&name).map_or(0, |op| op.taken(&self.stack));
+        let length = self.stack.len();
+        let operands = self.stack[length - taken..].to_vec();
+        if !self.dispatch(text, tx.clone()) {
+            return false;
+        }
+        self.after_operation(&name, length, operands, &tx);
+        true
+    }
+    // `length` is how long the stack was before the operation took `operands` off it
+    fn after_operation(&mut self, name: &str, length: usize, operands: Vec<Value>, tx: &Sender<Event>) {
+        let taken = operands.len();
+        // the fallback to floats is silent in the arithmetic itself, so `9223372036854775807 1 +` is caught here
+        let exact = EXACT_OPERATIONS.contains(&name) && !operands.is_empty() && operands.iter().all(Value::is_exact);
+        if exact && self.stack.last().is_some_and(|result| !result.is_exact()) {
+            // unwrap safety: rx lasts program lifetime
+            tx.send(Event::PushError(OpError::Warning(format!("The result of {name} is too big to stay exact, so it's a float")))).unwrap();
+        }
+        // like dup, which only pushed. a result equal to an operand still counts as taking it, like `5 0 +`
+        let only_pushed = self.stack.len() > length && self.stack[length - taken..length] == operands[..];
+        if taken > 0 && !only_pushed {
+            *self.last_x.borrow_mut() = operands;
+        }
+    }
+
+    fn dispatch(&mut self, text: String, tx: Sender<Event>) -> bool {
+        let name = text.to_lowercase();
+        // `5*succ` and `succ;5` run succ five times, as one entry
+        if let Some((count, op)) = split_repeat(&name) {
+            return match count.parse::<u64>() {
+                Ok(count) if count > MAX_REPEAT => {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(OpError::Domain(format!("Repeat count can be at most {MAX_REPEAT}, not {count}")))).unwrap();
+                    false
+                },
+                Ok(count) if count > 0 => self.repeat(op, count, tx),
+                _ => {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(OpError::Domain(format!("Repeat count must be a positive integer, not `{count}`")))).unwrap();
+                    false
+                },
+            };
+        }
+        // registers are named in the operation itself, like sto:x and rcl:x
+        if let Some(register) = name.strip_prefix("sto:").filter(|r| !r.is_empty()) {
+            return match self.stack.pop() {
+                Some(n) => {
+                    self.registers.insert(register.into(), n);
+                    true
+                },
+                None => {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(OpError::Domain("Nothing to store".into()))).unwrap();
+                    false
+                },
+            };
+        }
+        // conv:from:to converts between any two units of the same kind
+        if let Some(units) = name.strip_prefix("conv:") {
+            let conversion = units.split_once(':').and_then(|(from, to)| Some((Unit::parse(from)?, Unit::parse(to)?)));
+            let result = match conversion {
+                Some((from, to)) => match Operation::new_conversion(from, to) {
+                    Operation::Rust(_, function) => function(&mut self.stack, &mut self.angle_mode, self.output_base),
+                    _ => unreachable!(),
+                },
+                None => Err(format!("Unknown units in `{text}`")),
+            };
+            return match result {
+                Ok(()) => true,
+                Err(message) => {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(OpError::Domain(message))).unwrap();
+                    false
+                },
+            };
+        }
+        if let Some(register) = name.strip_prefix("rcl:").filter(|r| !r.is_empty()) {
+            return match self.registers.get(register) {
+                Some(value) => {
+                    self.stack.push(value.clone());
+                    true
+                },
+                None => {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(format!("Register {register} is empty").into())).unwrap();
+                    false
+                },
+            };
+        }
+        if let Some((command, argument)) = find_command(&name) {
+            return match (command.run)(self, argument, &tx) {
+                Ok(()) => true,
+                Err(e) => {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(e.into())).unwrap();
+                    false
+                },
+            };
+        }
+        // aliases can't form cycles, but a reload can remove what one points at
+        if let Some(Operation::Alias(target)) = self.operations.get(&name) {
+            let target = target.clone();
+            if !self.operations.contains_key(&target) && find_command(&target).is_none() {
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(format!("{name} is an alias for {target}, which no longer exists").into())).unwrap();
+                return false;
+            }
+            return self.operate(target, tx);
+        }
+        if let Some(Operation::Macro(tokens)) = self.operations.get(&name) {
+            let tokens = tokens.clone();
+            return self.run_macro(&tokens, tx);
+        }
+        if !self.operations.contains_key(&name) {
+            // unwrap safety: rx lasts program lifetime
+            tx.send(Event::PushError(OpError::Unknown(text))).unwrap();
+            return false;
+        }
+        if let Some(needed) = self.operations.get(&name).and_then(Operation::arity) {
+            let found = self.stack.len();
+            if found < needed {
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(OpError::Arity {op: name, needed, found})).unwrap();
+                return false;
+            }
+        }
+        match self.prepare_call(&name) {
+            Some(Ok(call)) => {
+                let outcome = call.run(&self.lua, &self.lua_shared, &mut self.uiua, &self.cancel);
+                return self.apply_outcome(&name, outcome, &tx);
+            },
+            Some(Err(e)) => {
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(e)).unwrap();
+                return false;
+            },
+            None => {},
+        }
         self.operations
-            .get(&text.to_lowercase())
-            .map_or(false, |op| match op {
-                Operation::Rust(function) => function(&mut self.stack),
-                Operation::Uiua(function) => {
-                    let arg_count = function.signature().args;
-                    if self.stack.len() >= arg_count {
-                        // panic safety: length checked first
-                        let (_, stack_top) = self.stack.split_at(self.stack.len() - arg_count);
-                        for i in stack_top {
-                            self.uiua.push(*i);
-                        }
-                        let result = self.uiua.call(function.clone());
-                        let uiua_stack = self.uiua.take_stack();
-                        match result {
-                            Ok(()) => {
-                                let mut out = Vec::with_capacity(uiua_stack.len());
-                                for i in uiua_stack {
-                                    match i.as_num(&self.uiua, "") {
-                                        Ok(n) => out.push(n),
-                                        Err(e) => {
-                                            // unwrap safety: rx lasts program lifetime
-                                            tx.send(Event::PushError(e.message())).unwrap();
-                                            return false;
-                                        },
-                                    }
-                                }
-                                for _ in 0..arg_count {self.stack.pop();}
-                                self.stack.extend(out);
-                                true
-                            },
-                            Err(e) => {
-                                // unwrap safety: rx lasts program lifetime
-                                tx.send(Event::PushError(e.message())).unwrap();
-                                false
-                            }
-                        }
-                    } else {
+            .get(&name)
+            .is_some_and(|op| match op {
+                Operation::Rust(_, function) => match function(&mut self.stack, &mut self.angle_mode, self.output_base) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        // unwrap safety: rx lasts program lifetime
+                        tx.send(Event::PushError(OpError::Domain(e))).unwrap();
                         false
-                    }
+                    },
                 },
-                Operation::Lua(name, arg_count) => {
-                    let table = self.lua.globals().get::<_, Table>("_ripen_registry").unwrap();
-                    let function = table.get::<_, mlua::Function>(name.as_str()).unwrap();
-                    if self.stack.len() >= *arg_count {
-                        // panic safety: length checked first
-                        let (_, stack_top) = self.stack.split_at(self.stack.len() - arg_count);
-                        let out: mlua::Result<Variadic<f64>> = function.call(Variadic::from_iter(stack_top.iter().copied()));
-                        match out {
-                            Ok(out) => {
-                                for _ in 0..*arg_count {self.stack.pop();}
-                                self.stack.extend(out.iter());
-                                true
-                            },
-                            Err(e) => {
-                                // unwrap safety: rx lasts program lifetime
-                                tx.send(Event::PushError(e.to_string())).unwrap();
-                                false
-                            }
-                        }
-                    } else {
-                        false
-                    }
+                Operation::UiuaConst(value) => {
+                    self.stack.push(value.clone());
+                    true
                 },
+                // handled above: macros need all of self, and Lua and Uiua calls go through Call
+                Operation::Macro(_) | Operation::Alias(_) | Operation::Lua(..) | Operation::LuaStack(_) | Operation::Uiua(_) => unreachable!(),
             })
     }
+    // copies the inputs of a Lua or Uiua operation off the stack. None for any other operation
+    fn prepare_call(&self, name: &str) -> Option<Result<Call, OpError>> {
+        let kind = match self.operations.get(name)? {
+            Operation::Lua(function, arg_count) => {
+                let (under, top) = self.stack.split_at(self.stack.len().saturating_sub(*arg_count));
+                let Some(args) = top.iter().map(Value::as_real).collect::<Option<Vec<f64>>>() else {
+                    return Some(Err(OpError::Domain("Lua functions only take real numbers".into())));
+                };
+                // the arguments aren't lent, so ripen.pop starts under them
+                CallKind::Lua { function: function.clone(), args, under: under.to_vec(), whole_stack: false }
+            },
+            Operation::LuaStack(function) => {
+                let Some(args) = self.stack.iter().map(Value::as_real).collect::<Option<Vec<f64>>>() else {
+                    return Some(Err(OpError::Domain("Lua functions only take real numbers".into())));
+                };
+                // the whole stack is already the argument, so nothing is lent, but values pushed with ripen.push go on top
+                CallKind::Lua { function: function.clone(), args, under: vec![], whole_stack: true }
+            },
+            Operation::Uiua(function) => {
+                let arg_count = function.signature().args;
+                let args = self.stack[self.stack.len().saturating_sub(arg_count)..].iter().map(|value| match value {
+                    Value::Vector(v) => Some(uiua::Value::from(uiua::Array::from(v.as_slice()))),
+                    value => value.as_real().map(uiua::Value::from),
+                });
+                let Some(args) = args.collect::<Option<Vec<uiua::Value>>>() else {
+                    return Some(Err(OpError::Domain("Uiua functions can't take complex numbers or units".into())));
+                };
+                CallKind::Uiua { function: function.clone(), args }
+            },
+            _ => return None,
+        };
+        Some(Ok(Call { kind, timeout: self.operation_timeout, angle_mode: self.angle_mode, digits: self.settings.significant_digits }))
+    }
+
+    // puts what a call gave back on the stack, or reports why it failed and leaves the stack alone
+    fn apply_outcome(&mut self, name: &str, outcome: Outcome, tx: &Sender<Event>) -> bool {
+        let digits = self.settings.significant_digits;
+        match outcome {
+            Outcome::Lua { out, under, messages, whole_stack } => {
+                for message in messages {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(OpError::LuaError(message))).unwrap();
+                }
+                let out = match out {
+                    Ok(out) => out,
+                    Err(e) => {
+                        // unwrap safety: rx lasts program lifetime
+                        tx.send(Event::PushError(OpError::LuaError(e))).unwrap();
+                        return false;
+                    },
+                };
+                if whole_stack {
+                    // values that come back unchanged, even moved, keep what Lua can't see, like exact fractions
+                    let mut originals: Vec<Option<Value>> = self.stack.drain(..).map(Some).collect();
+                    self.stack = out.into_iter().map(|n| {
+                        originals.iter_mut()
+                            .find(|original| original.as_ref().and_then(Value::as_real) == Some(n))
+                            .and_then(Option::take)
+                            .unwrap_or_else(|| Value::from(round_to_significant(n, digits)))
+                    }).collect();
+                    // `under` is what ripen.push added
+                    self.stack.extend(under);
+                } else {
+                    self.stack = under;
+                    self.stack.extend(out.iter().map(|&n| Value::from(round_to_significant(n, digits))));
+                }
+                true
+            },
+            Outcome::Uiua { out, args, outputs } => match out {
+                Ok(out) => {
+                    let warning = uiua_output_warning(name, args, outputs, out.len());
+                    if let Some(warning) = warning.filter(|_| self.settings.warn_output_count) {
+                        // unwrap safety: rx lasts program lifetime
+                        tx.send(Event::PushError(warning)).unwrap();
+                    }
+                    self.stack.truncate(self.stack.len().saturating_sub(args));
+                    self.stack.extend(out);
+                    true
+                },
+                Err(e) => {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(OpError::UiuaError(e))).unwrap();
+                    false
+                },
+            },
+        }
+    }
+
+    // what the top of the stack would be if the text box were submitted now.
+    // Lua and Uiua operations aren't previewed since they could be slow or have side effects
+    fn preview(&self) -> Option<String> {
+        if self.text_box.is_empty() {
+            return None;
+        }
+        let mut stack = self.stack.clone();
+        if let Some(num) = self.parse_token(&self.text_box) {
+            stack.push(num);
+        } else if !self.preview_operation(&self.text_box, &mut stack) {
+            return None;
+        }
+        stack.last().map(|value| self.format_value(value))
+    }
+
+    fn preview_operation(&self, name: &str, stack: &mut Vec<Value>) -> bool {
+        match self.operations.get(&name.to_lowercase()) {
+            Some(Operation::Rust(_, function)) => function(stack, &mut self.angle_mode.clone(), self.output_base).is_ok(),
+            Some(Operation::Alias(target)) => self.preview_operation(target, stack),
+            Some(Operation::Macro(tokens)) => tokens.iter().all(|token| match self.parse_token(token) {
+                Some(num) => {
+                    stack.push(num);
+                    true
+                },
+                None => self.preview_operation(token, stack),
+            }),
+            _ => false,
+        }
+    }
+
+    // all or nothing: the stack is restored if any token fails
+    fn run_macro(&mut self, tokens: &[String], tx: Sender<Event>) -> bool {
+        let saved = self.stack.clone();
+        for token in tokens {
+            let success = if let Some(num) = self.parse_token(token) {
+                self.stack.push(num);
+                true
+            } else {
+                self.operate(token.clone(), tx.clone())
+            };
+            if !success {
+                self.stack = saved;
+                return false;
+            }
+        }
+        true
+    }
+    // like a macro, a repeat that fails partway through leaves the stack as it was
+    fn repeat(&mut self, op: &str, count: u64, tx: Sender<Event>) -> bool {
+        let saved = self.stack.clone();
+        let max = self.settings.max_stack;
+        let mut growth = 0u64;
+        for i in 0..count {
+            // checked before each repeat rather than after the entry, assuming the rest grow the stack as
+            // much as the last one did, so `1000000*dup` stops straight away. dropping the oldest values is
+            // left to limit_stack, as it is for any other entry
+            let projected = (self.stack.len() as u64).saturating_add(growth.saturating_mul(count - i));
+            if max != 0 && projected > max as u64 && self.settings.stack_overflow == OverflowPolicy::Reject {
+                self.stack = saved;
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(format!("{count} repeats of {op} would leave {projected} values, but the stack holds at most {max}").into())).unwrap();
+                return false;
+            }
+            let length = self.stack.len();
+            if !self.operate(op.into(), tx.clone()) {
+                self.stack = saved;
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(format!("{op} failed on repeat {} of {count}", i + 1).into())).unwrap();
+                return false;
+            }
+            growth = self.stack.len().saturating_sub(length) as u64;
+        }
+        true
+    }
+    // runs a line as if it were typed and submitted, returning what went wrong instead of queueing it.
+    // on failure the text box keeps whatever didn't run. messages from entries that worked, like
+    // `Copied 3 values`, still go to the error queue
+    fn eval_line(&mut self, line: &str) -> Result<(), OpError> {
+        let (tx, rx) = mpsc::channel();
+        let code = strip_comment(line);
+        if code.len() < line.len() {
+            // a line that's only a comment does nothing, where an empty one would repeat the last operation
+            if code.trim().is_empty() {
+                self.text_box.clear();
+                return Ok(());
+            }
+            self.text_box = code.trim_end().into();
+        } else {
+            self.text_box = line.into();
+        }
+        let workspace = self.workspace.clone();
+        run_entry(self, tx);
+        let switched = self.workspace != workspace;
+        self.entry_messages(&rx, switched)
+    }
+
+    // shows what an entry sent as notices and warnings if it worked, or makes one error of them if it didn't
+    fn entry_messages(&mut self, rx: &Receiver<Event>, switched: bool) -> Result<(), OpError> {
+        let mut messages: Vec<OpError> = rx.try_iter().filter_map(|event| match event {
+            Event::PushError(message) => Some(message),
+            _ => None,
+        }).collect();
+        // everything that succeeds clears the text box, except that switching workspaces brings back
+        // what was typed in the other one
+        if self.text_box.is_empty() || switched {
+            for message in messages {
+                self.push_error(message);
+            }
+            Ok(())
+        } else if messages.len() > 1 {
+            Err(OpError::Other(messages.iter().map(OpError::to_string).collect::<Vec<String>>().join("; ")))
+        } else {
+            Err(messages.pop().unwrap_or_else(|| format!("Unable to run `{}`", self.text_box).into()))
+        }
+    }
+
+    // runs each whitespace separated token left to right. on failure, what's been done stays done
+    // and the text box is left holding the failed token and everything after it
+    fn operate_line(&mut self, tx: Sender<Event>) -> bool {
+        let tokens = tokenize(&self.text_box);
+        for (i, token) in tokens.iter().enumerate() {
+            let error = if let Some(num) = self.parse_token(token) {
+                self.stack.push(num);
+                continue;
+            } else if !self.is_operation(token) {
+                OpError::Unknown(token.clone())
+            } else if self.operate_counted(token.clone(), tx.clone()) {
+                self.previous = token.clone();
+                continue;
+            } else {
+                format!("Stopped at `{token}`").into()
+            };
+            self.text_box = tokens[i..].join(" ");
+            // unwrap safety: rx lasts program lifetime
+            tx.send(Event::PushError(error)).unwrap();
+            return false;
+        }
+        self.text_box.clear();
+        true
+    }
     fn operate_from_input(&mut self, tx: Sender<Event>) -> bool {
         let text = self.text_box.clone();
-        self.operate(text, tx)
+        self.operate_counted(text, tx)
     }
     fn operate_previous(&mut self, tx: Sender<Event>) -> bool {
         let text = self.previous.clone();
-        self.operate(text, tx)
+        self.operate_counted(text, tx)
     }
 
-    fn reset(&mut self) {
-        self.stack = Vec::new();
-        self.text_box.clear();
-        self.previous.clear();
+    // replaces the stack with `op` reduced over every run of `size` consecutive values,
+    // so it shrinks by size - 1. `op` is applied until one value is left, so binary
+    // operations fold over the window
+    fn window(&mut self, size: usize, op: &str, tx: Sender<Event>) -> Result<(), String> {
+        if size == 0 || size > self.stack.len() {
+            return Err(format!("Window size must be between 1 and {}", self.stack.len()));
+        }
+        let saved = mem::take(&mut self.stack);
+        let mut out = Vec::with_capacity(saved.len() + 1 - size);
+        for values in saved.windows(size) {
+            self.stack = values.to_vec();
+            while self.stack.len() > 1 {
+                let before = self.stack.len();
+                if !self.operate(op.into(), tx.clone()) || self.stack.len() >= before {
+                    self.stack = saved;
+                    return Err(format!("{op} can't be used to reduce a window"));
+                }
+            }
+            out.extend(self.stack.pop());
+        }
+        self.stack = out;
+        Ok(())
     }
 
-    fn load_lua<'a>(&'a mut self, lua_config: impl AsChunk<'a, 'static>) -> Result<(), mlua::Error> {
-        let (name_tx, name_rx) = mpsc::channel();
-        if self.no_lua_loaded {
-            self.lua.globals().set("_ripen_registry", self.lua.create_table()?)?;
+    // takes `name = tokens`, as typed after `def`. returns the defined name
+    fn define(&mut self, definition: &str) -> Result<String, String> {
+        let (name, body) = definition.split_once('=').ok_or("Expected `def <name> = <tokens>`")?;
+        let name = name.trim().to_lowercase();
+        if name.is_empty() || name.contains(char::is_whitespace) || (parse_number(&name).is_some() && !is_special_literal(&name)) {
+            return Err(format!("`{name}` is not a valid operation name"));
+        }
+        if find_command(&name).is_some() {
+            return Err(format!("`{name}` is a command, so it can't be redefined"));
+        }
+        let tokens = tokenize(body);
+        if tokens.is_empty() {
+            return Err(format!("Definition of {name} is empty"));
+        }
+        if let Some(token) = tokens.iter().find(|t| self.parse_token(t).is_none() && !self.is_operation(t)) {
+            return Err(format!("Unknown operation `{token}` in definition of {name}"));
         }
-        self.no_lua_loaded = false;
+        // definitions can only refer to existing operations, so checking at definition time rules out all cycles
+        if self.macro_uses(&tokens, &name) {
+            return Err(format!("{name} can't refer to itself"));
+        }
+        self.operations.insert(name.clone(), Operation::Macro(tokens));
+        Ok(name)
+    }
 
-        let lua_register_function = self.lua.create_function(move |lua, (name, arg_count, func): (String, usize, mlua::Function)| {
-            lua.globals().get::<_, Table>("_ripen_registry")?.set(name.clone(), func)?;
-            // unwrap safety: rx guaranteed not to have hung up
-            name_tx.send((name, arg_count)).unwrap();
-            Ok(mlua::Value::Nil)
-        })?;
-        self.lua.globals().set("register", lua_register_function)?;
-        self.lua.load(lua_config).exec()?;
-        for (name, arg_count) in name_rx.try_iter() {
-            self.operations.insert(name.clone().to_lowercase(), Operation::Lua(name, arg_count));
+    // like parse_number, but `inf`, `nan`, and `eps` are only numbers if no operation has taken the name
+    fn parse_token(&self, token: &str) -> Option<Value> {
+        if is_special_literal(token) && self.is_operation(token) {
+            return None;
+        }
+        match token.to_lowercase().as_str() {
+            "eps" | "+eps" => Some(f64::EPSILON.into()),
+            "-eps" => Some((-f64::EPSILON).into()),
+            _ => parse_number(token),
         }
-        Ok(())
     }
 
-    fn load_uiua(&mut self, uiua_config: impl AsRef<std::path::Path>) -> UiuaResult<()> {
-        self.uiua.run_file(uiua_config)?;
-        for (k, f) in self.uiua.bound_functions() {
-            self.operations.insert(k.to_string().to_lowercase(), Operation::Uiua(f));
+    // follows aliases to the operation they end at
+    fn resolve(&self, name: &str) -> Option<&Operation> {
+        match self.operations.get(name)? {
+            Operation::Alias(target) => self.resolve(target),
+            op => Some(op),
         }
-        Ok(())
     }
-}
 
-impl Operation {
-    fn new_rust<const N: usize>(op: impl Fn(&[f64; N]) -> Vec<f64> + 'static) -> Self {
-        Self::Rust(Box::new(move |v| {
-            if v.len() < N {
-                return false;
+    // maps each alias in aliases.toml to its target. loaded last, so aliases can name any operation.
+    // returns one message per alias that couldn't be added
+    fn load_aliases(&mut self, path: impl AsRef<Path>) -> Vec<String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return vec![],
+            Err(e) => return vec![format!("Unable to read aliases.toml: {e}")],
+        };
+        let table: HashMap<String, String> = match toml::from_str(&text) {
+            Ok(table) => table,
+            Err(e) => return vec![format!("Unable to parse aliases.toml: {e}")],
+        };
+        let mut errors = vec![];
+        let mut aliases = HashMap::new();
+        for (alias, target) in table {
+            let alias = alias.to_lowercase();
+            if self.operations.contains_key(&alias) {
+                errors.push(format!("Alias `{alias}` is already the name of an operation"));
+            } else if find_command(&alias).is_some() {
+                errors.push(format!("Alias `{alias}` is already the name of a command"));
+            } else {
+                aliases.insert(alias, target.to_lowercase());
             }
-            // unwrap safety: we just checked length
-            let (_, nums) = v.split_last_chunk::<N>().unwrap();
-            let out = op(nums);
-            for _ in 0..N {v.pop();}
-            v.extend(out);
-            true
-        }))
+        }
+        let mut valid = vec![];
+        for (alias, target) in &aliases {
+            // follow the chain through other aliases until it reaches an operation, a dead end, or itself
+            let mut seen = vec![alias];
+            let mut current = target;
+            let error = loop {
+                if seen.contains(&current) {
+                    break Some(format!("Alias `{alias}` is part of a cycle"));
+                }
+                match aliases.get(current) {
+                    Some(next) => {
+                        seen.push(current);
+                        current = next;
+                    },
+                    None if self.operations.contains_key(current) || find_command(current).is_some() => break None,
+                    None => break Some(format!("Alias `{alias}` points at `{current}`, which doesn't exist")),
+                }
+            };
+            match error {
+                Some(error) => errors.push(error),
+                None => valid.push((alias.clone(), target.clone())),
+            }
+        }
+        for (alias, target) in valid {
+            self.operations.insert(alias, Operation::Alias(target));
+        }
+        errors
     }
-}
-
 
-fn submit(c: &mut Calculator, tx: Sender<Event>) {
-    if let Ok(num) = c.text_box.parse::<f64>() {
-        c.stack.push(num);
-        c.previous = mem::take(&mut c.text_box);
-    } else if c.text_box.is_empty() {
-        c.operate_previous(tx);
-    } else if c.operate_from_input(tx) {
-        c.previous = mem::take(&mut c.text_box);
+    fn is_operation(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        let is_register = ["sto:", "rcl:"].iter().any(|p| name.strip_prefix(p).is_some_and(|r| !r.is_empty()));
+        is_register || name.starts_with("conv:") || self.operations.contains_key(&name) || find_command(&name).is_some()
+            || split_repeat(&name).is_some_and(|(_, op)| self.is_operation(op))
     }
-}
-
-fn main() -> Result<(), Box<dyn Error>>{
-    let project_dirs = ProjectDirs::from("", "", "ripen");
-    let config_dir = project_dirs.as_ref().map(ProjectDirs::config_local_dir);
-    let lua_config = config_dir.map(|p| p.join("functions.lua"));
-    let uiua_config = config_dir.map(|p| p.join("functions.ua"));
 
-    let mut app = Calculator::new();
-    let (tx, rx) = mpsc::channel();
-
-    // load lua
-    if let Err(e) = app.load_lua(include_str!("base.lua")) {
-        panic!("Error in the base lua config file! {e}");
+    fn macro_uses(&self, tokens: &[String], name: &str) -> bool {
+        tokens.iter().any(|token| {
+            let token = token.to_lowercase();
+            token == name || match self.operations.get(&token) {
+                Some(Operation::Macro(inner)) => self.macro_uses(inner, name),
+                Some(Operation::Alias(target)) => self.macro_uses(std::slice::from_ref(target), name),
+                _ => false,
+            }
+        })
     }
-    if let Some(lua_config) = lua_config {
-        if let Err(e) = app.load_lua(lua_config) {
-            // unwrap safety: rx lasts program lifetime
-            tx.send(Event::PushError(format!("Unable to load Lua config: {e}"))).unwrap();
+
+    // the operations a macro or alias calls directly, in the order they first appear
+    fn direct_uses(&self, op: &Operation) -> Vec<String> {
+        let tokens = match op {
+            Operation::Macro(tokens) => tokens.as_slice(),
+            Operation::Alias(target) => std::slice::from_ref(target),
+            _ => &[],
+        };
+        let mut uses = vec![];
+        for token in tokens.iter().map(|token| token.to_lowercase()) {
+            if self.operations.contains_key(&token) && !uses.contains(&token) {
+                uses.push(token);
+            }
         }
-    } else {
-        // unwrap safety: rx lasts program lifetime
-        tx.send(Event::PushError("Failed to construct Lua config path".into())).unwrap();
+        uses
     }
-    // TODO: load uiua
-    if let Some(uiua_config) = uiua_config {
-        if let Err(e) = app.load_uiua(uiua_config) {
-            // unwrap safety: rx lasts program lifetime
-            tx.send(Event::PushError(format!("Unable to load Uiua config: {e}"))).unwrap();
+
+    fn describe(&self, name: &str) -> Option<Overlay> {
+        let key = name.trim().to_lowercase();
+        if let Some(command) = COMMANDS.iter().find(|command| command.name == key) {
+            let lines = vec![command.about.into(), format!("Usage: {}", command.usage)];
+            return Some(Overlay { title: key, lines, scroll: 0 });
         }
-    } else {
-        // unwrap safety: rx lasts program lifetime
-        tx.send(Event::PushError("Failed to construct Lua config path".into())).unwrap();
+        let mut lines = match self.operations.get(&key)? {
+            Operation::Rust(..) => vec!["Built-in operation".into()],
+            Operation::Uiua(function) => vec![format!("Uiua function {} with signature {}", function.id, function.signature())],
+            Operation::UiuaConst(value) => vec![format!("Uiua constant {}", value.to_literal())],
+            Operation::Lua(name, arg_count) => vec![format!("Lua function {name} reading {arg_count} values")],
+            Operation::LuaStack(name) => vec![format!("Lua function {name} reading the whole stack")],
+            Operation::Macro(tokens) => vec![format!("def {key} = {}", tokens.join(" "))],
+            Operation::Alias(target) => vec![format!("Alias for {target}")],
+        };
+        let uses = self.direct_uses(&self.operations[&key]);
+        let mut used_by: Vec<&String> = self.operations.iter()
+            .filter(|(_, op)| self.direct_uses(op).contains(&key))
+            .map(|(name, _)| name)
+            .collect();
+        used_by.sort();
+        if !uses.is_empty() {
+            lines.push(format!("Uses: {}", uses.join(", ")));
+        }
+        if !used_by.is_empty() {
+            lines.push(format!("Used by: {}", used_by.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", ")));
+        }
+        if let Some(source) = self.sources.get(&key) {
+            lines.push(String::new());
+            lines.extend(source.lines().map(String::from));
+        }
+        Some(Overlay { title: key, lines, scroll: 0 })
     }
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    // every operation and command, alphabetically, with where it came from and how many values it reads.
+    // commands show how they're used instead
+    fn help(&self) -> Overlay {
+        let mut names: Vec<&str> = self.operations.keys().map(String::as_str).chain(COMMANDS.iter().map(|c| c.name)).collect();
+        names.sort();
+        let width = names.iter().map(|name| name.chars().count()).max().unwrap_or(0);
+        let lines = names.into_iter().map(|name| match self.operations.get(name) {
+            Some(op) => {
+                let hint = self.resolve(name).map_or("(varies)".into(), Operation::hint);
+                format!("{name:<width$}  {:<7}  {hint}", op.source())
+            },
+            // unwrap safety: every name not in operations came from COMMANDS
+            None => format!("{name:<width$}  {:<7}  {}", "command", COMMANDS.iter().find(|c| c.name == name).unwrap().usage),
+        }).collect();
+        Overlay { title: "Operations".into(), lines, scroll: 0 }
+    }
 
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // adds what was entered and the top of the stack it left, like `2 3 + = 5`
+    fn record_tape(&mut self, input: &str) {
+        let result = self.stack.last().map_or("(empty)".into(), |value| self.format_value(value));
+        self.tape.push(format!("{input} = {result}"));
+    }
 
-    let keyboard_tx = tx.clone();
+    // the whole tape, one entry per line, to tape.txt in the data directory
+    fn save_tape(&self) -> Result<PathBuf, String> {
+        let path = self.data_dir.as_ref().map(|d| d.join("tape.txt")).ok_or("No writable data directory")?;
+        let text: String = self.tape.iter().map(|line| format!("{line}\n")).collect();
+        fs::write(&path, text).map_err(|e| format!("Unable to write {}: {e}", path.display()))?;
+        Ok(path)
+    }
 
-    thread::spawn(move || {
-        let tx = keyboard_tx;
-        let mut last_tick = Instant::now();
-        let tick_rate = Duration::from_millis(200);
-        loop {
-            // Timeout is duration until next tick
-            let timeout = tick_rate
-                .checked_sub(Instant::now() - last_tick)
-                .unwrap_or(Duration::from_secs(0));
-            // Wait for events within that duration and send them over the mpsc channel
-            // unwrap safety: fatal
-            if event::poll(timeout).unwrap() {
-            // unwrap safety: fatal
-                if let CEvent::Key(key) = event::read().unwrap() {
-                    if key.code == KeyCode::Char('d') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                        // unwrap safety: rx lasts program lifetime
-                        tx.send(Event::Quit).unwrap();
-                    } else if key.code == KeyCode::Char('w') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                        // unwrap safety: rx lasts program lifetime
-                        tx.send(Event::ClearTextBox).unwrap();
-                    } else if key.code == KeyCode::Char('l') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                        // unwrap safety: rx lasts program lifetime
-                        tx.send(Event::Reset).unwrap();
-                    } else if key.code == KeyCode::Enter {
-                        // unwrap safety: rx lasts program lifetime
-                        tx.send(Event::Submit).unwrap();
-                    } else {
-                        // unwrap safety: rx lasts program lifetime
-                        tx.send(Event::Input(key)).unwrap();
-                    }
-                }
-            }
-            // If no inputs received during that time, send a tick event
-            if (Instant::now() - last_tick) >= tick_rate && tx.send(Event::Tick).is_ok() {
-                last_tick = Instant::now();
-            }
+    // adds an entry to the script. if the stack was changed some other way first, like by undo or a click,
+    // the script clears it and pushes it back as it was, so a replay still ends up the same
+    fn record_script(&mut self, before: &[Value], input: &str) {
+        if before != self.script_stack {
+            self.script.push("clear".into());
+            self.script.extend(before.iter().map(Value::to_literal));
         }
-    });
+        self.script.push(input.into());
+        self.script_stack = self.stack.clone();
+    }
 
-    loop {
-        // Draw
-        terminal.draw(|f| {
-            let window = f.size();
-            let stack_size = Rect { height: window.height - 3, ..window };
-            let stack = Paragraph::new(
-                app.stack.iter()
-                         .map(|number| Spans::from(Span::raw(format!("{}", number))))
-                         .collect::<Vec<Spans>>()
-                )
-                .scroll(((app.stack.len() as u16).saturating_sub(stack_size.height - 2), 0))
-                .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded));
-            let box_size = Rect { height: 3, y: window.height - 3, ..window};
-            let text_box = Paragraph::new(Span::from(format!("{}_", app.text_box)))
-                .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded));
-            f.render_widget(stack, stack_size);
-            f.render_widget(text_box, box_size);
-            
-            let corner_box = Rect::new(window.width * 2/3, 1, window.width / 3 - 2, stack_size.height - 2);
-            let error = Paragraph::new(app.errors.iter().map(Span::raw).map(Spans::from).collect::<Vec<Spans>>()).wrap(Wrap {trim: true});
-            f.render_widget(error, corner_box);
-        })?;
+    // the script to replay with `ripen --batch`, to session.ripen in the data directory unless a path is given
+    fn export_script(&mut self, path: Option<&str>) -> Result<PathBuf, String> {
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => self.data_dir.as_ref().map(|d| d.join("session.ripen")).ok_or("No writable data directory")?,
+        };
+        let stack = self.stack.clone();
+        // catches up with anything done since the last entry
+        if stack != self.script_stack {
+            self.script.push("clear".into());
+            self.script.extend(stack.iter().map(Value::to_literal));
+            self.script_stack = stack;
+        }
+        let mut text = String::from("# RiPeN session, replay with ripen --batch\n");
+        for line in &self.script {
+            text.push_str(line);
+            text.push('\n');
+        }
+        fs::write(&path, text).map_err(|e| format!("Unable to write {}: {e}", path.display()))?;
+        Ok(path)
+    }
 
-        // Handle events
-        match rx.recv().unwrap() {
-            Event::Quit => break,
-            Event::Input(KeyEvent {code: KeyCode::Backspace, ..}) => { app.text_box.pop(); },
-            Event::Input(KeyEvent {code: KeyCode::Char(chr), ..}) => { app.text_box.push(chr); }
-            Event::Submit => { submit(&mut app, tx.clone()); },
-            Event::Reset => { app.reset(); },
-            Event::ClearTextBox => { mem::take(&mut app.text_box); },
-            Event::Tick | Event::Input(..) => {},
-            Event::PushError(e) => {
-                app.errors.push_back(e);
-                let tx = tx.clone();
-                thread::spawn(move || {
-                    thread::sleep(Duration::from_secs(4));
-                    // unwrap safety: rx lasts program lifetime
-                    tx.send(Event::PopError).unwrap();
-                });
-            },
-            Event::PopError => { app.errors.pop_front(); }
+    fn session_script(&self) -> Result<PathBuf, String> {
+        self.data_dir.as_ref().map(|d| d.join("session.lua")).ok_or_else(|| "No writable data directory".into())
+    }
+
+    // the script is plain Lua calling push(value) from the bottom of the stack up,
+    // then sto(name, value) for each register
+    fn export_lua(&self) -> Result<PathBuf, String> {
+        let path = self.session_script()?;
+        let literal = |n: &Value| match n.as_real() {
+            Some(n) if n.is_nan() => Ok("0/0".into()),
+            Some(n) if n.is_infinite() && n > 0.0 => Ok("math.huge".into()),
+            Some(n) if n.is_infinite() => Ok("-math.huge".into()),
+            Some(n) => Ok(format!("{n:?}")),
+            None => Err("Only real numbers can be exported to Lua".to_string()),
+        };
+        let mut script = String::from("-- RiPeN session, restore with importlua\n");
+        for n in &self.stack {
+            script.push_str(&format!("push({})\n", literal(n)?));
+        }
+        let mut registers: Vec<_> = self.registers.iter().collect();
+        registers.sort_by_key(|(name, _)| name.as_str());
+        for (name, n) in registers {
+            script.push_str(&format!("sto({name:?}, {})\n", literal(n)?));
         }
+        fs::write(&path, script).map_err(|e| format!("Unable to write {}: {e}", path.display()))?;
+        Ok(path)
     }
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    // runs in its own Lua state so the script can't touch registered functions
+    fn import_lua(&mut self) -> Result<(), String> {
+        let path = self.session_script()?;
+        let lua = Lua::new();
+        let (value_tx, value_rx) = mpsc::channel();
+        let push = lua.create_function(move |_, n: f64| {
+            // unwrap safety: rx guaranteed not to have hung up
+            value_tx.send(n).unwrap();
+            Ok(())
+        }).map_err(|e| e.to_string())?;
+        let (register_tx, register_rx) = mpsc::channel();
+        let sto = lua.create_function(move |_, (name, n): (String, f64)| {
+            // unwrap safety: rx guaranteed not to have hung up
+            register_tx.send((name.to_lowercase(), n)).unwrap();
+            Ok(())
+        }).map_err(|e| e.to_string())?;
+        lua.globals().set("push", push).map_err(|e| e.to_string())?;
+        lua.globals().set("sto", sto).map_err(|e| e.to_string())?;
+        lua.load(path).exec().map_err(|e| format!("Unable to import session: {e}"))?;
+        self.stack = value_rx.try_iter().map(Value::from).collect();
+        self.registers = register_rx.try_iter().map(|(name, n)| (name, Value::from(n))).collect();
+        Ok(())
+    }
+
+    fn session_file(&self) -> Option<PathBuf> {
+        self.data_dir.as_ref().map(|d| d.join("session.json"))
+    }
+
+    fn save_session(&self) -> Result<(), String> {
+        let Some(path) = self.session_file() else { return Ok(()) };
+        let session = Session {
+            stack: self.stack.iter().map(|n| n.to_literal()).collect(),
+            history: self.history.clone(),
+            workspace: Some(self.workspace.clone()),
+            workspaces: self.workspaces.iter().map(|(name, workspace)| SavedWorkspace {
+                name: name.clone(),
+                stack: workspace.stack.iter().map(Value::to_literal).collect(),
+                history: workspace.history.clone(),
+            }).collect(),
+        };
+        persist::save(path, &session)
+    }
+
+    // puts the current stack, history, and undo aside and picks up where `name` left off, or starts it empty
+    fn switch_workspace(&mut self, name: &str) -> Result<(), String> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err("Workspace names can't be empty or contain spaces".into());
+        }
+        if name == self.workspace {
+            return Ok(());
+        }
+        let parked = Workspace {
+            stack: mem::take(&mut self.stack),
+            history: mem::take(&mut self.history),
+            previous: mem::take(&mut self.previous),
+            undo: mem::take(&mut self.undo),
+            redo: mem::take(&mut self.redo),
+            text_box: mem::take(&mut self.text_box),
+            last_x: mem::take(&mut *self.last_x.borrow_mut()),
+        };
+        self.workspaces.insert(mem::replace(&mut self.workspace, name.into()), parked);
+        let Workspace { stack, history, previous, undo, redo, text_box, last_x } = self.workspaces.remove(name).unwrap_or_default();
+        (self.stack, self.history, self.previous, self.undo, self.redo, self.text_box) = (stack, history, previous, undo, redo, text_box);
+        *self.last_x.borrow_mut() = last_x;
+        self.cursor_back = 0;
+        self.history_index = self.history.len();
+        self.stack_scroll = 0;
+        self.selected = None;
+        self.last_push = None;
+        Ok(())
+    }
+
+    // the workspace after the current one, alphabetically, wrapping around
+    fn next_workspace(&self) -> Option<String> {
+        let mut names: Vec<&String> = self.workspaces.keys().collect();
+        names.sort();
+        names.iter().find(|name| **name > &self.workspace).or(names.first()).map(|name| name.to_string())
+    }
+
+    fn usage_file(&self) -> Option<PathBuf> {
+        self.data_dir.as_ref().map(|d| d.join("usage.json"))
+    }
+
+    fn load_usage(&mut self) -> Result<(), String> {
+        let Some(path) = self.usage_file() else { return Ok(()) };
+        self.usage = persist::load_usage(path)?;
+        Ok(())
+    }
+
+    fn save_usage(&self) -> Result<(), String> {
+        let Some(path) = self.usage_file() else { return Ok(()) };
+        persist::save_usage(path, &self.usage)
+    }
+
+    // runs an operation typed as an entry, counting it if it works. a repeat like `5*succ` counts as five succs
+    fn operate_counted(&mut self, text: String, tx: Sender<Event>) -> bool {
+        let name = text.to_lowercase();
+        if !self.operate(text, tx) {
+            return false;
+        }
+        let (op, times) = match split_repeat(&name) {
+            Some((count, op)) => (op.to_string(), count.parse().unwrap_or(1)),
+            None => (name.clone(), 1),
+        };
+        *self.usage.entry(op).or_default() += times;
+        true
+    }
+
+    // the most used operations, with how many times each has run
+    fn usage_stats(&self, count: usize) -> Overlay {
+        let mut usage: Vec<(&String, &u64)> = self.usage.iter().collect();
+        // ties alphabetically, so the order doesn't change between draws
+        usage.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let width = usage.iter().take(count).map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+        let mut lines: Vec<String> = usage.into_iter().take(count).map(|(name, uses)| format!("{name:<width$}  {uses}")).collect();
+        if lines.is_empty() {
+            lines.push("No operations have been run yet".into());
+        }
+        Overlay { title: "Most used".into(), lines, scroll: 0 }
+    }
+
+    fn restore_session(&mut self) -> Result<(), String> {
+        let Some(path) = self.session_file() else { return Ok(()) };
+        let Some(session) = persist::load(path)? else { return Ok(()) };
+        // the limit may have been lowered since the session was saved, so each stack keeps its newest values
+        let max = self.settings.max_stack;
+        let parse = |stack: &[String]| {
+            let mut stack = stack.iter()
+                .map(|literal| Value::parse(literal).ok_or_else(|| format!("Saved session is corrupt: `{literal}` is not a number")))
+                .collect::<Result<Vec<Value>, String>>()?;
+            if max != 0 && stack.len() > max {
+                stack.drain(..stack.len() - max);
+            }
+            Ok::<_, String>(stack)
+        };
+        self.stack = parse(&session.stack)?;
+        for saved in &session.workspaces {
+            let workspace = Workspace { stack: parse(&saved.stack)?, history: saved.history.clone(), ..Workspace::default() };
+            self.workspaces.insert(saved.name.clone(), workspace);
+        }
+        self.workspace = session.workspace.unwrap_or_else(|| DEFAULT_WORKSPACE.into());
+        self.history = session.history;
+        self.history_index = self.history.len();
+        Ok(())
+    }
+
+    // applies stack_overflow once an entry has left more than max_stack values. rejecting puts back
+    // the stack from before the entry and the entry itself, so it counts as failed
+    fn limit_stack(&mut self, before: &[Value], entry: &str) -> Result<(), String> {
+        let max = self.settings.max_stack;
+        if max == 0 || self.stack.len() <= max {
+            return Ok(());
+        }
+        match self.settings.stack_overflow {
+            OverflowPolicy::Reject => {
+                let length = self.stack.len();
+                self.stack = before.to_vec();
+                self.text_box = entry.into();
+                Err(format!("That would leave {length} values, but the stack holds at most {max}"))
+            },
+            OverflowPolicy::DropOldest => {
+                self.stack.drain(..self.stack.len() - max);
+                Ok(())
+            },
+        }
+    }
+
+    fn definitions_file(&self) -> Option<PathBuf> {
+        self.data_dir.as_ref().map(|d| d.join("definitions.txt"))
+    }
+
+    fn save_definition(&self, definition: &str) -> io::Result<()> {
+        let Some(path) = self.definitions_file() else { return Ok(()) };
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", definition.trim())
+    }
+
+    // later lines win, so redefinitions from a previous session are replayed in order
+    fn load_definitions(&mut self) -> Vec<String> {
+        let Some(text) = self.definitions_file().and_then(|p| fs::read_to_string(p).ok()) else { return vec![] };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| self.define(line).err())
+            .collect()
+    }
+
+    // takes the stack from before an action, and only saves it if the action changed anything
+    fn record_undo(&mut self, before: Vec<Value>) {
+        if before == self.stack {
+            return;
+        }
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(before);
+        self.redo.clear();
+    }
+
+    fn undo(&mut self) {
+        if let Some(stack) = self.undo.pop_back() {
+            self.redo.push(mem::replace(&mut self.stack, stack));
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(stack) = self.redo.pop() {
+            self.undo.push_back(mem::replace(&mut self.stack, stack));
+        }
+    }
+
+    fn format_number(&self, n: Number) -> String {
+        if self.output_base != Base::Decimal {
+            if let Some(text) = n.as_real().and_then(|n| self.output_base.format(n)) {
+                return text;
+            }
+        }
+        match (self.display_format, n) {
+            (DisplayFormat::Auto, Number::Rational(r)) => r.to_string(),
+            (format, Number::Complex(re, im)) => {
+                let sign = if im < 0.0 { '-' } else { '+' };
+                format!("{}{sign}{}i", format.format(re), format.format(im.abs()))
+            },
+            (format, n) => format.format(n.to_f64()),
+        }
+    }
+
+    fn format_value(&self, value: &Value) -> String {
+        match value {
+            Value::Scalar(n) => self.format_number(*n),
+            Value::Vector(v) => format!("[{}]", v.iter().map(|&n| self.format_number(n.into())).collect::<Vec<_>>().join(" ")),
+            // always decimal, since `0x1fft` couldn't be read back
+            Value::Quantity(n, unit) => format!("{}{}", self.display_format.format(*n), unit.name()),
+        }
+    }
+
+    // how the stack pane colors a value, with color_by_sign on
+    fn value_style(&self, value: &Value) -> Style {
+        if !self.settings.color_by_sign {
+            return Style::default();
+        }
+        let reals = match value {
+            Value::Scalar(Number::Complex(re, im)) => vec![*re, *im],
+            Value::Scalar(n) => vec![n.to_f64()],
+            Value::Vector(v) => v.clone(),
+            Value::Quantity(n, _) => vec![*n],
+        };
+        match reals.as_slice() {
+            reals if reals.iter().any(|n| !n.is_finite()) => self.theme.warning,
+            // only a single real number has a sign
+            [n] if matches!(value, Value::Scalar(_) | Value::Quantity(..)) && *n < 0.0 => self.theme.negative,
+            [n] if matches!(value, Value::Scalar(_) | Value::Quantity(..)) && *n == 0.0 => Style::default().add_modifier(Modifier::DIM),
+            _ => Style::default(),
+        }
+    }
+
+    // like format_value, but with digits grouped and values left in decimal marked so they aren't mistaken
+    // for the chosen base. copies leave both off so they still parse
+    fn display_value(&self, value: &Value) -> String {
+        let text = self.format_value(value);
+        let text = match self.settings.digit_grouping.separator() {
+            Some(separator) => group_digits(&text, separator),
+            None => text,
+        };
+        let in_base = |n: f64| self.output_base.format(n).is_some();
+        let decimal = match value {
+            Value::Scalar(n) => !n.as_real().is_some_and(in_base),
+            Value::Vector(v) => !v.iter().all(|&n| in_base(n)),
+            Value::Quantity(..) => true,
+        };
+        if self.output_base != Base::Decimal && decimal {
+            format!("{text} (dec)")
+        } else {
+            text
+        }
+    }
+
+    // pops a selector: 0 auto, 1 fixed, 2 scientific, 3 engineering.
+    // fixed and scientific then pop the number of digits after the decimal point
+    fn set_format_from_stack(&mut self) -> Result<(), String> {
+        let usage = "Expected `<selector> fmt`, or `<digits> <selector> fmt` for fixed and scientific";
+        let selector = self.stack.last().ok_or(usage)?.to_f64();
+        let needs_digits = DisplayFormat::needs_digits(selector);
+        let digits = if needs_digits {
+            Some(self.stack.iter().nth_back(1).ok_or(usage)?.to_f64())
+        } else {
+            None
+        };
+        self.display_format = DisplayFormat::from_selector(selector, digits).ok_or(usage)?;
+        self.stack.truncate(self.stack.len() - if needs_digits { 2 } else { 1 });
+        Ok(())
+    }
+
+    // only the first line is kept, since a newline would be a submit
+    fn paste(&mut self, text: &str) -> Result<(), String> {
+        let line = text.lines().next().unwrap_or_default().trim();
+        if line.is_empty() {
+            return Err("Nothing to paste".into());
+        }
+        self.text_box.extend(line.chars().filter(|c| !c.is_control()));
+        self.history_index = self.history.len();
+        Ok(())
+    }
+
+    fn copy_top(&self) -> Result<String, String> {
+        let top = self.format_value(self.stack.last().ok_or("Nothing to copy")?);
+        clipboard::write(&top)?;
+        Ok(top)
+    }
+
+    // one value per line, oldest first, so pasting into another RPN tool keeps the order
+    fn copy_stack(&self) -> Result<usize, String> {
+        if self.stack.is_empty() {
+            return Err("Nothing to copy".into());
+        }
+        let lines: Vec<String> = self.stack.iter().map(|value| self.format_value(value)).collect();
+        clipboard::write(&lines.join("\n"))?;
+        Ok(lines.len())
+    }
+
+    // the word being typed, after any earlier tokens and up to the cursor
+    fn current_word(&self) -> &str {
+        self.text_box[..self.cursor_index()].rsplit(char::is_whitespace).next().unwrap_or_default()
+    }
+
+    // where the cursor is, as a byte index into the text box
+    fn cursor_index(&self) -> usize {
+        let chars = self.text_box.chars().count();
+        let back = self.cursor_back.min(chars);
+        self.text_box.char_indices().nth(chars - back).map_or(self.text_box.len(), |(i, _)| i)
+    }
+
+    fn move_cursor(&mut self, to: CursorMove) {
+        let chars = self.text_box.chars().count();
+        self.cursor_back = match to {
+            CursorMove::Left => (self.cursor_back + 1).min(chars),
+            CursorMove::Right => self.cursor_back.saturating_sub(1).min(chars),
+            CursorMove::Home => chars,
+            CursorMove::End => 0,
+        };
+    }
+
+    fn insert_char(&mut self, chr: char) {
+        let index = self.cursor_index();
+        self.text_box.insert(index, chr);
+    }
+
+    // removes the character before the cursor, if there is one
+    fn delete_back(&mut self) {
+        let index = self.cursor_index();
+        if let Some((start, _)) = self.text_box[..index].char_indices().next_back() {
+            self.text_box.remove(start);
+        }
+    }
+
+    fn delete_forward(&mut self) {
+        let index = self.cursor_index();
+        if index < self.text_box.len() {
+            self.text_box.remove(index);
+            self.cursor_back = self.cursor_back.saturating_sub(1);
+        }
+    }
+
+    // takes a number that was just entered back off the stack and into the text box, to fix a typo
+    fn pop_to_entry(&mut self) -> Result<(), String> {
+        if !self.text_box.is_empty() {
+            return Err("The entry box has to be empty to take a value back".into());
+        }
+        let (text, previous, depth) = self.last_push.take().ok_or("Nothing was just entered")?;
+        let top = self.stack.last().map(Value::to_literal);
+        if self.stack.len() != depth || top != self.parse_token(&text).map(|v| v.to_literal()) {
+            return Err("The last entry has already been used".into());
+        }
+        let before = self.stack.clone();
+        self.stack.pop();
+        self.record_undo(before);
+        self.text_box = text;
+        self.previous = previous;
+        self.selected = None;
+        Ok(())
+    }
+
+    // shows how the operation being typed is defined
+    fn inspect(&mut self) -> Result<(), String> {
+        let name = self.current_word();
+        if name.is_empty() {
+            return Err("Type an operation's name to see its definition".into());
+        }
+        self.overlay = Some(self.describe(name).ok_or_else(|| format!("No operation named {name}"))?);
+        Ok(())
+    }
+
+    // the EE key starts an exponent, but only right after a plain decimal number that doesn't have one yet
+    fn enter_exponent(&mut self) -> Result<(), String> {
+        let word = self.current_word();
+        if split_exponent(word).is_some() {
+            return Err("The number already has an exponent".into());
+        }
+        // the cursor can be partway through the number, so the digits after it become the exponent
+        let index = self.cursor_index();
+        let rest = self.text_box[index..].split(char::is_whitespace).next().unwrap_or_default();
+        if !is_mantissa(word) || !rest.chars().all(|c| c.is_ascii_digit()) {
+            return Err("EE goes after the digits of a number".into());
+        }
+        self.text_box.insert(index, 'e');
+        Ok(())
+    }
+
+    // spells out a number being typed with an exponent, like `3 × 10^8`, or `3 × 10^_` before its digits
+    fn exponent_hint(&self) -> Option<String> {
+        let (mantissa, exponent) = split_exponent(self.current_word())?;
+        let exponent = if exponent.trim_start_matches(['-', '+']).is_empty() { format!("{exponent}_") } else { exponent.into() };
+        Some(format!("{mantissa} × 10^{exponent}"))
+    }
+
+    fn completions(&self, prefix: &str) -> Vec<&str> {
+        let prefix = prefix.to_lowercase();
+        let mut matches: Vec<&str> = self.operations.keys()
+            .map(String::as_str)
+            .chain(COMMANDS.iter().map(|command| command.name))
+            .filter(|name| name.starts_with(&prefix))
+            .collect();
+        matches.sort();
+        matches
+    }
+
+    // the names matching what was typed, and which of them Tab last filled in.
+    // editing the word since the last Tab starts over from what's typed now
+    fn suggestions(&self) -> (&str, Vec<&str>, Option<usize>) {
+        if let Some((prefix, i)) = &self.completion {
+            let matches = self.completions(prefix);
+            if matches.get(*i) == Some(&self.current_word()) {
+                return (prefix, matches, Some(*i));
+            }
+        }
+        let word = self.current_word();
+        if word.is_empty() || self.parse_token(word).is_some() {
+            return (word, vec![], None);
+        }
+        (word, self.completions(word), None)
+    }
+
+    // replaces the current word with the next operation name it's a prefix of
+    fn complete(&mut self) {
+        let (prefix, matches, selected) = self.suggestions();
+        if matches.is_empty() {
+            return;
+        }
+        let index = selected.map_or(0, |i| (i + 1) % matches.len());
+        let (prefix, name) = (prefix.to_string(), matches[index].to_string());
+        // anything after the cursor stays where it is, after the name
+        let index = self.cursor_index();
+        let start = index - self.current_word().len();
+        self.text_box.replace_range(start..index, &name);
+        self.completion = Some((prefix, index));
+    }
+
+    fn push_error(&mut self, error: impl Into<OpError>) {
+        let now = Instant::now();
+        let error = error.into();
+        self.log.push((now, error.to_string()));
+        self.errors.push_back((error, self.error_timeout.map(|timeout| now + timeout)));
+    }
+
+    fn push_startup_error(&mut self, message: String) {
+        self.log.push((Instant::now(), message.clone()));
+        self.startup_errors.push(message);
+    }
+
+    // the first line of the stack pane in view, for a pane `height` rows tall including its border
+    fn stack_view(&self, height: u16) -> u16 {
+        let max_scroll = (self.stack.len() as u16).saturating_sub(height.saturating_sub(2));
+        let offset = self.stack_scroll.min(max_scroll);
+        // either way, an offset of 0 keeps the newest values in view
+        match self.settings.growth_direction {
+            GrowthDirection::Down => max_scroll - offset,
+            GrowthDirection::Up => offset,
+        }
+    }
+
+    // above the text box, sharing the width with the tape when it's shown
+    fn stack_pane(&self, window: Rect) -> Rect {
+        let height = window.height.saturating_sub(4);
+        let width = if self.show_tape { window.width * 2 / 3 } else { window.width };
+        Rect { height, width, ..window }
+    }
+
+    // the index into the stack of the value shown at a point on the screen
+    fn stack_index_at(&self, window: Rect, column: u16, row: u16) -> Option<usize> {
+        let pane = self.stack_pane(window);
+        // with --inline the pane doesn't start at the top of the screen
+        let (Some(column), Some(row)) = (column.checked_sub(pane.x), row.checked_sub(pane.y)) else { return None };
+        // the border takes the first and last rows
+        if row == 0 || row >= pane.height.saturating_sub(1) || column >= pane.width {
+            return None;
+        }
+        let line = (self.stack_view(pane.height) + row - 1) as usize;
+        match self.settings.growth_direction {
+            GrowthDirection::Down => (line < self.stack.len()).then_some(line),
+            GrowthDirection::Up => self.stack.len().checked_sub(line + 1),
+        }
+    }
+
+    // pushes a copy of a clicked value
+    fn stack_click(&mut self, index: usize) {
+        let Some(value) = self.stack.get(index).cloned() else { return };
+        let before = self.stack.clone();
+        self.stack.push(value);
+        // a rejected click puts back the text box as it was, so whatever was being typed stays
+        let entry = self.text_box.clone();
+        match self.limit_stack(&before, &entry) {
+            Ok(()) => self.record_undo(before),
+            Err(e) => self.push_error(e),
+        }
+    }
+
+    // starts out on the newest value
+    fn toggle_stack_edit(&mut self) -> Result<(), String> {
+        match self.mode {
+            Mode::StackEdit => self.leave_stack_edit(),
+            Mode::Entry if self.stack.is_empty() => return Err("Nothing on the stack to edit".into()),
+            Mode::Entry => {
+                self.mode = Mode::StackEdit;
+                self.selected = Some(self.stack.len() - 1);
+            },
+        }
+        Ok(())
+    }
+
+    fn leave_stack_edit(&mut self) {
+        self.mode = Mode::Entry;
+        self.selected = None;
+    }
+
+    // keeps the selection on the stack after anything that shrinks it, and leaves once it's empty
+    fn clamp_selection(&mut self) {
+        if self.mode != Mode::StackEdit {
+            return;
+        }
+        match self.stack.len().checked_sub(1) {
+            Some(last) => self.selected = self.selected.map(|i| i.min(last)),
+            None => self.leave_stack_edit(),
+        }
+    }
+
+    // scrolls as needed to keep the selection among the `rows` in view
+    fn move_selection(&mut self, older: bool, rows: u16) {
+        let Some(index) = self.selected else { return };
+        let index = if older { index.saturating_sub(1) } else { (index + 1).min(self.stack.len().saturating_sub(1)) };
+        self.selected = Some(index);
+        let depth = (self.stack.len() - 1 - index) as u16;
+        self.stack_scroll = self.stack_scroll.clamp(depth.saturating_sub(rows.saturating_sub(1)), depth);
+    }
+
+    fn delete_selected(&mut self) {
+        let Some(index) = self.selected else { return };
+        let before = self.stack.clone();
+        self.stack.remove(index);
+        self.record_undo(before);
+        self.clamp_selection();
+    }
+
+    // the value goes into the text box exactly as it's stored, and what the edit enters takes its place
+    fn edit_selected(&mut self) {
+        let Some(index) = self.selected else { return };
+        let before = self.stack.clone();
+        let value = self.stack.remove(index);
+        self.record_undo(before);
+        self.text_box = value.to_literal();
+        self.mode = Mode::Entry;
+    }
+
+    // moves what an entry pushed down to where the edited value was, as long as it only pushed
+    fn place_edited(&mut self, index: usize, before: &[Value]) {
+        if self.stack.len() > before.len() && self.stack.starts_with(before) {
+            let added: Vec<Value> = self.stack.drain(before.len()..).collect();
+            let index = index.min(self.stack.len());
+            self.stack.splice(index..index, added);
+        }
+    }
+
+    // returns how many were removed
+    fn expire_errors(&mut self, now: Instant) -> usize {
+        let before = self.errors.len();
+        while self.errors.front().is_some_and(|(_, expiry)| expiry.is_some_and(|e| e <= now)) {
+            self.errors.pop_front();
+        }
+        before - self.errors.len()
+    }
+
+    fn history_prev(&mut self) {
+        if self.history_index > 0 {
+            self.history_index -= 1;
+            self.text_box = self.history[self.history_index].clone();
+            // what replaced an edited value's text isn't an edit anymore
+            self.selected = None;
+        }
+    }
+
+    fn history_next(&mut self) {
+        if self.history_index < self.history.len() {
+            self.history_index += 1;
+            self.text_box = self.history.get(self.history_index).cloned().unwrap_or_default();
+            self.selected = None;
+        }
+    }
+
+    // with confirm_reset, the first press only arms the reset and a second one in time clears everything
+    fn request_reset(&mut self, now: Instant) {
+        if self.settings.confirm_reset && self.armed_reset.is_none_or(|armed| now > armed + RESET_WINDOW) {
+            self.armed_reset = Some(now);
+            return;
+        }
+        self.armed_reset = None;
+        let before = self.stack.clone();
+        self.reset();
+        self.record_undo(before);
+    }
+
+    // returns whether an armed reset ran out of time
+    fn expire_reset(&mut self, now: Instant) -> bool {
+        let expired = self.armed_reset.is_some_and(|armed| now > armed + RESET_WINDOW);
+        if expired {
+            self.armed_reset = None;
+        }
+        expired
+    }
+
+    fn reset(&mut self) {
+        self.stack = Vec::new();
+        self.text_box.clear();
+        self.previous.clear();
+        self.mode = Mode::Entry;
+        self.selected = None;
+    }
+
+    // adds to the registry left by earlier loads rather than replacing it.
+    // returns the names this load added that no earlier load had registered
+    fn load_lua<'a>(&'a mut self, lua_config: impl AsChunk<'a, 'static>) -> Result<HashSet<String>, mlua::Error> {
+        let (name_tx, name_rx) = mpsc::channel();
+        if self.lua.globals().get::<_, Option<Table>>("_ripen_registry")?.is_none() {
+            self.lua.globals().set("_ripen_registry", self.lua.create_table()?)?;
+        }
+        if self.lua.globals().get::<_, Option<Table>>("ripen")?.is_none() {
+            self.lua.globals().set("ripen", Self::ripen_table(&self.lua, &self.lua_shared)?)?;
+        }
+
+        // a count of None means the function takes the whole stack
+        let register = move |lua: &Lua, name: String, arg_count: Option<usize>, func: mlua::Function| {
+            let info = func.info();
+            let registry = lua.globals().get::<_, Table>("_ripen_registry")?;
+            let new = !registry.contains_key(name.clone())?;
+            registry.set(name.clone(), func)?;
+            // unwrap safety: rx guaranteed not to have hung up
+            name_tx.send((name, arg_count, info.line_defined.zip(info.last_line_defined), new)).unwrap();
+            Ok(mlua::Value::Nil)
+        };
+        let register_auto = register.clone();
+        let register_stack = register.clone();
+        let lua_register_function = self.lua.create_function(move |lua, (name, arg_count, func): (String, usize, mlua::Function)| {
+            register(lua, name, Some(arg_count), func)
+        })?;
+        // like register, but reads the number of values from the function's parameter list
+        let lua_register_auto_function = self.lua.create_function(move |lua, (name, func): (String, mlua::Function)| {
+            let Some(arg_count) = lua_arity(&func) else {
+                return Err(mlua::Error::RuntimeError(format!("can't tell how many values {name} reads; use register with a count instead")));
+            };
+            register_auto(lua, name, Some(arg_count), func)
+        })?;
+        let lua_register_stack_function = self.lua.create_function(move |lua, (name, func): (String, mlua::Function)| {
+            register_stack(lua, name, None, func)
+        })?;
+        self.lua.globals().set("register", lua_register_function)?;
+        self.lua.globals().set("register_auto", lua_register_auto_function)?;
+        self.lua.globals().set("register_stack", lua_register_stack_function)?;
+        // read the source ourselves so function definitions can be shown later
+        let chunk_name = lua_config.name();
+        let source = lua_config.source().map_err(mlua::Error::external)?;
+        let mut chunk = self.lua.load(source.as_ref());
+        if let Some(chunk_name) = chunk_name {
+            chunk = chunk.set_name(chunk_name);
+        }
+        Self::limit_lua(&self.lua, self.operation_timeout, &self.cancel, || chunk.exec())?;
+        let text = String::from_utf8_lossy(&source);
+        let mut added = HashSet::new();
+        for (name, arg_count, lines, new) in name_rx.try_iter() {
+            let key = name.to_lowercase();
+            if new {
+                added.insert(key.clone());
+            }
+            // functions defined in C, like math.min, have no lines
+            match lines {
+                Some((first, last)) if first > 0 => {
+                    let definition = text.lines().skip(first - 1).take(last + 1 - first).collect::<Vec<_>>().join("\n");
+                    self.sources.insert(key.clone(), definition);
+                },
+                _ => { self.sources.remove(&key); },
+            }
+            // Uiua takes priority whichever file is loaded first
+            if !matches!(self.operations.get(&key), Some(Operation::Uiua(_) | Operation::UiuaConst(_))) {
+                let operation = match arg_count {
+                    Some(arg_count) => Operation::Lua(name, arg_count),
+                    None => Operation::LuaStack(name),
+                };
+                self.operations.insert(key, operation);
+            }
+        }
+        Ok(added)
+    }
+
+    // ripen.push(n), ripen.pop(), ripen.depth(), and ripen.error(message) work on the stack under a running
+    // function's arguments. ripen.angle is set to "rad", "deg", or "grad" before each call
+    fn ripen_table<'lua>(lua: &'lua Lua, shared: &Arc<Mutex<LuaShared>>) -> mlua::Result<Table<'lua>> {
+        // each function gets the shared state, but only during a call
+        fn lent<R>(shared: &Mutex<LuaShared>, name: &str, f: impl FnOnce(&mut LuaShared) -> mlua::Result<R>) -> mlua::Result<R> {
+            let mut shared = shared.lock().unwrap_or_else(PoisonError::into_inner);
+            if !shared.lending {
+                return Err(mlua::Error::RuntimeError(format!("ripen.{name} only works while a registered function runs")));
+            }
+            f(&mut shared)
+        }
+        let table = lua.create_table()?;
+        let push = shared.clone();
+        table.set("push", lua.create_function(move |_, n: f64| lent(&push, "push", |shared| {
+            shared.stack.push(Value::from(n));
+            Ok(())
+        }))?)?;
+        let pop = shared.clone();
+        table.set("pop", lua.create_function(move |_, ()| lent(&pop, "pop", |shared| {
+            let Some(top) = shared.stack.last() else { return Ok(None) };
+            let n = top.as_real().ok_or_else(|| mlua::Error::RuntimeError("ripen.pop: the top value isn't a real number".into()))?;
+            shared.stack.pop();
+            Ok(Some(n))
+        }))?)?;
+        let depth = shared.clone();
+        table.set("depth", lua.create_function(move |_, ()| lent(&depth, "depth", |shared| Ok(shared.stack.len())))?)?;
+        let error = shared.clone();
+        table.set("error", lua.create_function(move |_, message: String| lent(&error, "error", |shared| {
+            shared.messages.push(message);
+            Ok(())
+        }))?)?;
+        Ok(table)
+    }
+
+    // runs `call` with `stack` lent to the ripen table. returns what's left of the stack and any messages
+    fn lend_stack<R>(lua: &Lua, shared: &Mutex<LuaShared>, stack: Vec<Value>, angle_mode: AngleMode, call: impl FnOnce() -> mlua::Result<R>) -> (mlua::Result<R>, Vec<Value>, Vec<String>) {
+        *shared.lock().unwrap_or_else(PoisonError::into_inner) = LuaShared { stack, messages: vec![], lending: true };
+        let out = lua.globals().get::<_, Table>("ripen")
+            .and_then(|ripen| ripen.set("angle", angle_mode.name().to_lowercase()))
+            .and_then(|()| call());
+        let shared = mem::take(&mut *shared.lock().unwrap_or_else(PoisonError::into_inner));
+        (out, shared.stack, shared.messages)
+    }
+
+    // runs `call` under the operation timeout, or until `cancel` is set. Lua only checks every so many instructions
+    fn limit_lua<R>(lua: &Lua, timeout: Option<Duration>, cancel: &Arc<AtomicBool>, call: impl FnOnce() -> mlua::Result<R>) -> mlua::Result<R> {
+        let deadline = timeout.map(|timeout| (timeout, Instant::now() + timeout));
+        let cancel = cancel.clone();
+        lua.set_hook(HookTriggers::new().every_nth_instruction(10_000), move |_, _| {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(mlua::Error::RuntimeError("stopped".into()));
+            }
+            match deadline {
+                Some((timeout, deadline)) if Instant::now() > deadline => {
+                    Err(mlua::Error::RuntimeError(format!("stopped after running for {}s", timeout.as_secs_f64())))
+                },
+                _ => Ok(()),
+            }
+        });
+        let out = call();
+        lua.remove_hook();
+        out
+    }
+
+    // starts over with a fresh Lua state, so functions deleted from the config go away
+    fn reload_lua<'a>(&'a mut self, lua_config: impl AsChunk<'a, 'static>) -> Result<(), mlua::Error> {
+        self.operations.retain(|_, op| !matches!(op, Operation::Lua(..) | Operation::LuaStack(_)));
+        self.prune_sources();
+        self.lua = Lua::new();
+        self.load_lua(BASE_LUA)?;
+        self.load_lua(lua_config).map(drop)
+    }
+
+    fn load_uiua(&mut self, uiua_config: impl AsRef<Path>) -> UiuaResult<()> {
+        self.uiua.run_file(uiua_config.as_ref())?;
+        // each call restarts the execution limit with run_asm, which would run the top level again
+        let mut asm = self.uiua.take_asm();
+        asm.remove_top_level();
+        self.uiua.run_asm(asm)?;
+        let text = fs::read_to_string(uiua_config).unwrap_or_default();
+        self.bind_uiua(&text);
+        Ok(())
+    }
+
+    // adds an operation for every function and constant the runtime has bound. `text` is the code that bound them
+    fn bind_uiua(&mut self, text: &str) {
+        let functions = self.uiua.bound_functions().into_iter().map(|(k, f)| (k, Ok(Operation::Uiua(f))));
+        // constants are numbers or lists of them, which come back as vectors like function results do
+        let constants = self.uiua.bound_values().into_iter().map(|(k, value)| {
+            let digits = self.settings.significant_digits;
+            let constant = if value.rank() == 1 {
+                value.as_nums(&self.uiua, "").map(|v| Value::Vector(v.into_iter().map(|n| round_to_significant(n, digits)).collect()))
+            } else {
+                value.as_num(&self.uiua, "").map(|n| Value::from(round_to_significant(n, digits)))
+            };
+            (k, constant.map(Operation::UiuaConst).map_err(|_| value.shape().to_string()))
+        }).collect::<Vec<_>>();
+        for (k, operation) in functions.chain(constants) {
+            let name = k.to_string();
+            let key = name.to_lowercase();
+            let operation = match operation {
+                Ok(operation) => operation,
+                Err(shape) => {
+                    self.log.push((Instant::now(), format!("Skipped Uiua constant {name}, which isn't a number or list of numbers (shape {shape})")));
+                    continue;
+                },
+            };
+            // bindings look like `Name ← body` or `Name = body`
+            let definition = text.lines().find(|line| {
+                line.trim_start()
+                    .strip_prefix(name.as_str())
+                    .is_some_and(|rest| rest.trim_start().starts_with(['←', '=']))
+            });
+            match definition {
+                Some(line) => { self.sources.insert(key.clone(), line.trim().to_string()); },
+                None => { self.sources.remove(&key); },
+            }
+            self.operations.insert(key, operation);
+        }
+    }
+
+    fn fresh_uiua(timeout: Option<Duration>) -> Uiua {
+        let uiua = Uiua::with_safe_sys();
+        match timeout {
+            Some(timeout) => uiua.with_execution_limit(timeout),
+            None => uiua,
+        }
+    }
+
+    fn reload_uiua(&mut self, uiua_config: impl AsRef<Path>) -> UiuaResult<()> {
+        // functions from the old runtime can't be called from a fresh one
+        self.operations.retain(|_, op| !matches!(op, Operation::Uiua(_) | Operation::UiuaConst(_)));
+        self.prune_sources();
+        self.uiua = Self::fresh_uiua(self.operation_timeout);
+        self.load_uiua(uiua_config)
+    }
+
+    fn prune_sources(&mut self) {
+        self.sources.retain(|name, _| self.operations.contains_key(name));
+    }
+}
+
+impl Call {
+    // needs nothing from the calculator but its interpreters, so it can run on a thread of its own
+    fn run(self, lua: &Lua, shared: &Mutex<LuaShared>, uiua: &mut Uiua, cancel: &Arc<AtomicBool>) -> Outcome {
+        let Call { kind, timeout, angle_mode, digits } = self;
+        match kind {
+            CallKind::Lua { function, args, under, whole_stack } => {
+                let (out, under, messages) = Calculator::lend_stack(lua, shared, under, angle_mode, || {
+                    let function = lua.globals().get::<_, Table>("_ripen_registry")?.get::<_, mlua::Function>(function)?;
+                    Calculator::limit_lua(lua, timeout, cancel, || if whole_stack {
+                        function.call::<_, Vec<f64>>(args)
+                    } else {
+                        function.call::<_, Variadic<f64>>(Variadic::from_iter(args)).map(|out| out.to_vec())
+                    })
+                });
+                Outcome::Lua { out: out.map_err(|e| e.to_string()), under, messages, whole_stack }
+            },
+            CallKind::Uiua { function, args } => {
+                let signature = function.signature();
+                // the execution limit counts from the last run_asm, so restart it for this call. the config's
+                // top level was dropped when it loaded, so nothing runs again
+                let asm = uiua.take_asm();
+                let result = uiua.run_asm(asm).and_then(|()| {
+                    // anything left by an earlier call would come back as output
+                    uiua.take_stack();
+                    for arg in args {
+                        uiua.push(arg);
+                    }
+                    uiua.call(function)
+                });
+                let uiua_stack = uiua.take_stack();
+                let out = match result {
+                    Ok(()) => uiua_stack.into_iter().map(|i| {
+                        // lists come back as vectors
+                        if i.rank() == 1 {
+                            i.as_nums(uiua, "").map(|v| Value::Vector(v.into_iter().map(|n| round_to_significant(n, digits)).collect()))
+                        } else {
+                            i.as_num(uiua, "").map(|n| Value::from(round_to_significant(n, digits)))
+                        }
+                    }).collect::<UiuaResult<Vec<Value>>>().map_err(|e| e.message()),
+                    Err(e) => {
+                        // a failure partway through can leave state that take_stack doesn't clear, like a half
+                        // built array, so start a fresh runtime with the same code. functions stay valid
+                        let asm = uiua.take_asm();
+                        *uiua = Calculator::fresh_uiua(timeout);
+                        match uiua.run_asm(asm) {
+                            Ok(()) => Err(e.message()),
+                            Err(restart) => Err(format!("{}, and restarting Uiua failed: {}", e.message(), restart.message())),
+                        }
+                    },
+                };
+                Outcome::Uiua { out, args: signature.args, outputs: signature.outputs }
+            },
+        }
+    }
+}
+
+impl Operation {
+    fn source(&self) -> &'static str {
+        match self {
+            Self::Rust(..) => "builtin",
+            Self::Uiua(_) | Self::UiuaConst(_) => "Uiua",
+            Self::Lua(..) | Self::LuaStack(_) => "Lua",
+            Self::Macro(_) => "def",
+            Self::Alias(_) => "alias",
+        }
+    }
+    // None for macros, which check each operation as it runs
+    fn arity(&self) -> Option<usize> {
+        match self {
+            Self::Rust(Arity::Fixed(arity), _) | Self::Lua(_, arity) => Some(*arity),
+            Self::Rust(Arity::Counted, _) => Some(1),
+            Self::Rust(Arity::Stack, _) => Some(0),
+            Self::LuaStack(_) | Self::UiuaConst(_) => Some(0),
+            Self::Uiua(function) => Some(function.signature().args),
+            Self::Macro(_) | Self::Alias(_) => None,
+        }
+    }
+    // the stack effect as shown next to the name, like `(2)`. only Uiua functions know their output count
+    fn hint(&self) -> String {
+        match self {
+            Self::Uiua(function) => format!("({}→{})", function.signature().args, function.signature().outputs),
+            Self::LuaStack(_) => "(stack)".into(),
+            Self::UiuaConst(_) => "(0→1)".into(),
+            op => op.arity().map_or("(varies)".into(), |n| format!("({n})")),
+        }
+    }
+    // how many values off the top running this on `stack` would take. a count that isn't valid takes nothing,
+    // since the operation will fail
+    fn taken(&self, stack: &[Value]) -> usize {
+        let taken = match self {
+            Self::Rust(Arity::Counted, _) => stack.last()
+                .and_then(Value::as_real)
+                .filter(|n| *n >= 0.0 && n.fract() == 0.0)
+                .map_or(0, |n| (n as usize).saturating_add(1)),
+            Self::Rust(Arity::Stack, _) | Self::LuaStack(_) => stack.len(),
+            op => op.arity().unwrap_or(0),
+        };
+        taken.min(stack.len())
+    }
+    // for operations that work on the whole stack and can't fail
+    fn new_stack(op: impl Fn(&mut Vec<Value>) + 'static) -> Self {
+        Self::Rust(Arity::Stack, Box::new(move |v, _, _| {
+            op(v);
+            Ok(())
+        }))
+    }
+    // sorts the whole stack, oldest first. fails unless every value is a real number
+    // the values themselves are moved, so fractions stay exact. numbers with units are compared in the
+    // unit of the first one, and can't be mixed with plain numbers
+    fn new_sort(compare: impl Fn(&f64, &f64) -> std::cmp::Ordering + 'static) -> Self {
+        Self::Rust(Arity::Stack, Box::new(move |v, _, _| {
+            let unit = v.iter().find_map(|value| match value {
+                Value::Quantity(_, unit) => Some(*unit),
+                _ => None,
+            });
+            let key = |value: &Value| match (value, unit) {
+                (Value::Quantity(n, from), Some(unit)) => from.convert(*n, unit),
+                (Value::Quantity(..), None) => unreachable!(),
+                (value, None) => value.as_real().ok_or_else(|| "Only real numbers can be sorted".into()),
+                (_, Some(_)) => Err("Can't sort numbers with units alongside numbers without".into()),
+            };
+            let keys = v.iter().map(key).collect::<Result<Vec<f64>, String>>()?;
+            let mut keyed: Vec<(f64, Value)> = keys.into_iter().zip(v.drain(..)).collect();
+            keyed.sort_by(|(a, _), (b, _)| compare(a, b));
+            *v = keyed.into_iter().map(|(_, value)| value).collect();
+            Ok(())
+        }))
+    }
+    // replaces the whole stack with `reduce` over it. an empty stack fails, unless empty_total says to push `identity`
+    fn new_total(identity: Option<Number>, empty: Rc<Cell<EmptyTotal>>, reduce: impl Fn(&[Number]) -> Result<Number, String> + 'static) -> Self {
+        Self::Rust(Arity::Stack, Box::new(move |v, _, _| {
+            if v.is_empty() {
+                return match identity.filter(|_| empty.get() == EmptyTotal::Identity) {
+                    Some(identity) => {
+                        v.push(identity.into());
+                        Ok(())
+                    },
+                    None => Err("The stack is empty".into()),
+                };
+            }
+            let Some(nums) = v.iter().map(Value::scalar).collect::<Option<Vec<Number>>>() else {
+                return Err("Only a stack of plain numbers can be reduced".into());
+            };
+            *v = vec![reduce(&nums)?.into()];
+            Ok(())
+        }))
+    }
+    // reads `start stop step` (step on top) and reduces the half-open range [start, stop)
+    fn new_range(reduce: impl Fn(&mut dyn Iterator<Item = f64>) -> f64 + 'static) -> Self {
+        Self::Rust(Arity::Fixed(3), Box::new(move |v, _, _| {
+            let Some([start, stop, step]) = v.last_chunk::<3>().map(|c| c.each_ref().map(Value::to_f64)) else { return Err("Not enough values".into()) };
+            if step == 0.0 || !(start.is_finite() && stop.is_finite() && step.is_finite()) {
+                return Err("Range needs a finite start, stop, and nonzero step".into());
+            }
+            // a step pointing away from stop gives an empty range
+            let count = ((stop - start) / step).ceil().max(0.0);
+            if count > 1e8 {
+                return Err("Range is too long".into());
+            }
+            let out = reduce(&mut (0..count as u64).map(|i| start + i as f64 * step));
+            v.truncate(v.len() - 3);
+            v.push(out.into());
+            Ok(())
+        }))
+    }
+    // for numeric operations that don't need exact values. fails on complex numbers
+    fn new_rust<const N: usize>(op: impl Fn(&[f64; N]) -> Vec<f64> + 'static) -> Self {
+        Self::new_angle(move |_, nums| op(nums))
+    }
+    // like new_rust, for operations that depend on the angle mode
+    fn new_angle<const N: usize>(op: impl Fn(AngleMode, &[f64; N]) -> Vec<f64> + 'static) -> Self {
+        Self::Rust(Arity::Fixed(N), Box::new(move |v, mode, _| {
+            let Some(nums) = v.last_chunk::<N>() else { return Err("Not enough values".into()) };
+            let Some(reals) = nums.iter().map(|n| n.as_real()).collect::<Option<Vec<f64>>>() else {
+                return Err("Expected real numbers".into());
+            };
+            // unwrap safety: collected from an array of length N
+            let out = op(*mode, &reals.try_into().unwrap());
+            v.truncate(v.len() - N);
+            v.extend(out.into_iter().map(Value::from));
+            Ok(())
+        }))
+    }
+    // for integer math. fails on anything that isn't a real integer small enough for a float to hold exactly
+    fn new_integer<const N: usize>(op: impl Fn([i64; N]) -> Result<Number, String> + 'static) -> Self {
+        Self::new_value(move |values: [Value; N]| {
+            let ints = values.iter()
+                .map(|v| v.as_real().filter(|n| n.fract() == 0.0 && n.abs() <= 2f64.powi(53)).map(|n| n as i64))
+                .collect::<Option<Vec<i64>>>()
+                .ok_or("Expected integers")?;
+            // unwrap safety: collected from an array of length N
+            Ok(vec![op(ints.try_into().unwrap())?.into()])
+        })
+    }
+    // works on integers as 64-bit two's complement. fails on non-integers, and whenever `op` gives None
+    fn new_bitwise<const N: usize>(op: impl Fn([i64; N]) -> Option<i64> + 'static) -> Self {
+        Self::new_rust(move |nums: &[f64; N]| {
+            let ints = nums.iter().map(|&n| (n.fract() == 0.0 && n.abs() < 2f64.powi(63)).then_some(n as i64)).collect::<Option<Vec<i64>>>();
+            // unwrap safety: collected from an array of length N
+            let out = ints.and_then(|ints| op(ints.try_into().unwrap()));
+            // NaN makes `checked` fail
+            vec![out.map_or(f64::NAN, |n| n as f64)]
+        }).checked("Bitwise operations need integers, and shifts from 0 to 63")
+    }
+    // a number with a unit converts to `to`, as long as it's the same kind. a plain number is taken to be in `from`
+    fn new_conversion(from: Unit, to: Unit) -> Self {
+        Self::new_value(move |[a]| match a {
+            Value::Quantity(n, unit) => Ok(vec![Value::Quantity(unit.convert(n, to)?, to)]),
+            a => {
+                let n = a.as_real().ok_or("Expected a real number")?;
+                Ok(vec![Value::from(from.convert(n, to)?)])
+            },
+        })
+    }
+    // fails on vectors and numbers with units
+    fn new_number<const N: usize>(op: impl Fn(&[Number; N]) -> Vec<Number> + 'static) -> Self {
+        Self::new_value(move |values: [Value; N]| {
+            let Some(nums) = values.iter().map(Value::scalar).collect::<Option<Vec<Number>>>() else {
+                return Err("Expected plain numbers".into());
+            };
+            // unwrap safety: collected from an array of length N
+            Ok(op(&nums.try_into().unwrap()).into_iter().map(Value::from).collect())
+        })
+    }
+    // for operations on any kind of value
+    fn new_value<const N: usize>(op: impl Fn([Value; N]) -> Result<Vec<Value>, String> + 'static) -> Self {
+        Self::Rust(Arity::Fixed(N), Box::new(move |v, _, _| {
+            let Some(values) = v.last_chunk::<N>() else { return Err("Not enough values".into()) };
+            let out = op(values.clone())?;
+            v.truncate(v.len() - N);
+            v.extend(out);
+            Ok(())
+        }))
+    }
+    // reduces the vector on top of the stack to one number
+    fn new_vector(op: impl Fn(&[f64]) -> Result<Number, String> + 'static) -> Self {
+        Self::Rust(Arity::Fixed(1), Box::new(move |v, _, _| {
+            let Some(Value::Vector(values)) = v.last() else { return Err("Expected a vector".into()) };
+            let out = op(values)?;
+            v.pop();
+            v.push(out.into());
+            Ok(())
+        }))
+    }
+    // pops a count n first, then gives `op` the n real numbers under it, oldest first
+    fn new_counted(op: impl Fn(&[f64]) -> Result<Value, String> + 'static) -> Self {
+        Self::new_counted_values(move |values| {
+            let Some(values) = values.iter().map(Value::as_real).collect::<Option<Vec<f64>>>() else {
+                return Err("Expected real numbers".into());
+            };
+            Ok(vec![op(&values)?])
+        })
+    }
+    // like new_counted, for operations on any kind of value
+    fn new_counted_values(op: impl Fn(&[Value]) -> Result<Vec<Value>, String> + 'static) -> Self {
+        Self::Rust(Arity::Counted, Box::new(move |v, _, _| {
+            let Some(count) = v.last().map(Value::to_f64) else { return Err("Not enough values".into()) };
+            if count < 0.0 || count.fract() != 0.0 {
+                return Err("Count must be a non-negative integer".into());
+            }
+            let count = count as usize;
+            if count >= v.len() {
+                return Err(format!("Count is {count}, but only {} values are under it", v.len() - 1));
+            }
+            let start = v.len() - 1 - count;
+            let out = op(&v[start..v.len() - 1])?;
+            v.truncate(start);
+            v.extend(out);
+            Ok(())
+        }))
+    }
+    // pops a depth n first, then gives `op` the stack, which is checked to have a value at depth n
+    fn new_depth(op: impl Fn(&mut Vec<Value>, usize) + 'static) -> Self {
+        Self::Rust(Arity::Fixed(1), Box::new(move |v, _, _| {
+            let Some(depth) = v.last().map(Value::to_f64) else { return Err("Not enough values".into()) };
+            if depth < 0.0 || depth.fract() != 0.0 {
+                return Err("Depth must be a non-negative integer".into());
+            }
+            // compared as a float, since a huge depth saturates when cast
+            if depth >= (v.len() - 1) as f64 {
+                return Err(format!("Depth is {depth}, but only {} values are under it", v.len() - 1));
+            }
+            v.pop();
+            op(v, depth as usize);
+            Ok(())
+        }))
+    }
+    // treats infinite or NaN results as bad input, failing with `message` instead of pushing them.
+    // operations that can meaningfully give those, like sqrt with complex results, leave this off
+    fn checked(self, message: &'static str) -> Self {
+        let Self::Rust(Arity::Fixed(arity), function) = self else { return self };
+        Self::Rust(Arity::Fixed(arity), Box::new(move |v, mode, base| {
+            // only the operands are kept to put back, since nothing under them is touched
+            let untouched = v.len().saturating_sub(arity);
+            let operands = v[untouched..].to_vec();
+            let result = function(v, mode, base);
+            if result.is_ok() && v[untouched.min(v.len())..].iter().all(Value::is_finite) {
+                return Ok(());
+            }
+            v.truncate(untouched);
+            v.extend(operands);
+            result.and(Err(message.into()))
+        }))
+    }
+}
+
+// a Uiua function that took `args` values and left `left`, when its signature says `outputs`.
+// Uiua checks signatures when it compiles, so a mismatch means something else went wrong
+fn uiua_output_warning(name: &str, args: usize, outputs: usize, left: usize) -> Option<OpError> {
+    if left != outputs {
+        Some(OpError::Warning(format!("{name} left {left} values, but its signature says {outputs}")))
+    } else if left == 0 && args > 0 {
+        // the signature agrees, but a function that eats its inputs is more often a mistake
+        Some(OpError::Warning(format!("{name} took {args} values and left none; undo brings them back")))
+    } else {
+        None
+    }
+}
+
+// digits can be grouped like `1_0000` or `1,000,000`. each separator needs a digit on both sides, and
+// commas need exactly three digits after them, so `1,,0` and `1,2,3` aren't numbers
+fn strip_separators(text: &str) -> Option<String> {
+    let hex = text.trim_start_matches('-').get(..2).is_some_and(|p| p.eq_ignore_ascii_case("0x"));
+    let is_digit = |c: &char| if hex { c.is_ascii_hexdigit() } else { c.is_ascii_digit() };
+    let chars: Vec<char> = text.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        let before = i.checked_sub(1).and_then(|i| chars.get(i));
+        let grouped = match c {
+            '_' => before.is_some_and(is_digit) && chars.get(i + 1).is_some_and(is_digit),
+            ',' => before.is_some_and(is_digit)
+                && chars[i + 1..].iter().take_while(|c| is_digit(c)).count() == 3,
+            _ => true,
+        };
+        if !grouped {
+            return None;
+        }
+    }
+    Some(text.replace([',', '_'], ""))
+}
+
+// + and - on numbers that might have units. the right one is converted to the left one's unit
+fn combine_units(a: Value, b: Value, op: fn(Number, Number) -> Number, verb: &str) -> Result<Vec<Value>, String> {
+    match (a, b) {
+        (Value::Scalar(a), Value::Scalar(b)) => Ok(vec![op(a, b).into()]),
+        (Value::Quantity(a, unit), Value::Quantity(b, other)) => {
+            let b = other.convert(b, unit).map_err(|_| format!("Can't {verb} {} and {}", unit.name(), other.name()))?;
+            Ok(vec![Value::Quantity(op(a.into(), b.into()).to_f64(), unit)])
+        },
+        (Value::Quantity(..), Value::Scalar(_)) | (Value::Scalar(_), Value::Quantity(..)) => {
+            Err(format!("Can't {verb} a number with a unit and one without"))
+        },
+        _ => Err(format!("Can't {verb} a vector and a number")),
+    }
+}
+
+// the value that sorts last by `compare`, kept as it is so fractions stay exact. fails on complex numbers
+fn stack_extreme(v: &[Number], compare: fn(&f64, &f64) -> std::cmp::Ordering) -> Result<Number, String> {
+    let reals = v.iter().map(|n| n.as_real()).collect::<Option<Vec<f64>>>().ok_or("Only real numbers can be compared")?;
+    v.iter().zip(&reals).max_by(|(_, a), (_, b)| compare(a, b)).map(|(&n, _)| n).ok_or_else(|| "The stack is empty".into())
+}
+
+// replaces the top value with its digits in `radix`, most significant first, so undigits reads them back
+// in the same order
+fn digits(v: &mut Vec<Value>, radix: u64) -> Result<(), String> {
+    let Some(n) = v.last().and_then(Value::as_real) else { return Err("digits needs a plain number".into()) };
+    if n < 0.0 || n.fract() != 0.0 || n >= 2f64.powi(64) {
+        return Err(format!("digits needs a non-negative integer, not {n}"));
+    }
+    let mut rest = n as u64;
+    let mut out = vec![];
+    loop {
+        out.push(Value::integer((rest % radix) as i64));
+        rest /= radix;
+        if rest == 0 {
+            break;
+        }
+    }
+    v.pop();
+    v.extend(out.into_iter().rev());
+    Ok(())
+}
+
+// pops the digit count first, then that many digits in `radix`
+fn undigits(v: &mut Vec<Value>, radix: u64) -> Result<(), String> {
+    let Some(count) = v.last().map(Value::to_f64) else { return Err("Not enough values".into()) };
+    if count < 0.0 || count.fract() != 0.0 || count as usize >= v.len() {
+        return Err(format!("undigits needs a digit count from 0 to {}, not {count}", v.len() - 1));
+    }
+    let start = v.len() - 1 - count as usize;
+    let digits: Vec<f64> = v[start..v.len() - 1].iter().map(Value::to_f64).collect();
+    if let Some(bad) = digits.iter().find(|&&d| !(0.0..radix as f64).contains(&d) || d.fract() != 0.0) {
+        return Err(format!("undigits needs digits from 0 to {} in base {radix}, not {bad}", radix - 1));
+    }
+    // exact unless it's too big for an i64
+    let exact = digits.iter().try_fold(0i64, |acc, &d| acc.checked_mul(radix as i64)?.checked_add(d as i64));
+    v.truncate(start);
+    v.push(exact.map_or_else(|| digits.iter().fold(0.0, |acc, d| acc * radix as f64 + d).into(), Value::integer));
+    Ok(())
+}
+
+// the least squares line through the points of two vectors: its slope, its intercept, and r², which is None when
+// every y is the same. fails unless both hold the same number of reals, with at least two different x values
+fn linear_fit(x: Value, y: Value) -> Result<(f64, f64, Option<f64>), String> {
+    let (Value::Vector(xs), Value::Vector(ys)) = (x, y) else { return Err("linreg needs two vectors, x then y".into()) };
+    if xs.len() != ys.len() {
+        return Err(format!("linreg needs vectors of the same length, not {} and {}", xs.len(), ys.len()));
+    }
+    if xs.len() < 2 {
+        return Err("linreg needs at least two points".into());
+    }
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let syy: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum();
+    let sxy: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    // every x is the same, so the line would be vertical
+    if sxx == 0.0 {
+        return Err("linreg needs at least two different x values".into());
+    }
+    let slope = sxy / sxx;
+    // rounding can take points on a perfect line a hair past 1
+    let r2 = (syy != 0.0).then(|| (sxy * sxy / (sxx * syy)).min(1.0));
+    Ok((slope, mean_y - slope * mean_x, r2))
+}
+
+// always non-negative, and 0 only when both are 0
+fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+// the safe Lua state has no debug library, so the parameter count is read from the function's
+// Lua 5.4 bytecode. None for varargs and C functions, which can't be dumped
+fn lua_arity(function: &mlua::Function) -> Option<usize> {
+    let bytes = function.dump(true);
+    if bytes.get(..5)? != b"\x1bLua\x54" {
+        return None;
+    }
+    // the header ends with the sizes of an instruction, an integer, and a float, then a sample of
+    // each of the last two. after that comes the upvalue count
+    let (int_size, float_size) = (*bytes.get(13)? as usize, *bytes.get(14)? as usize);
+    let mut rest = bytes.get(15 + int_size + float_size + 1..)?;
+    // the stripped source name and the first and last lines are variable length, ending in a byte with the high bit set
+    for _ in 0..3 {
+        let end = rest.iter().position(|b| b & 0x80 != 0)?;
+        rest = &rest[end + 1..];
+    }
+    match rest {
+        [params, 0, ..] => Some((*params).into()),
+        _ => None,
+    }
+}
+
+// decimal, a fraction, or an integer with a 0x, 0o, or 0b prefix. a prefix with no digits isn't a number.
+// real numbers in brackets, like `[1 2 3]`, are a vector
+fn parse_number(text: &str) -> Option<Value> {
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        return inner.split_whitespace().map(|n| parse_number(n)?.as_real()).collect::<Option<_>>().map(Value::Vector);
+    }
+    let grouped;
+    let text = if text.contains([',', '_']) {
+        grouped = strip_separators(text)?;
+        grouped.as_str()
+    } else {
+        text
+    };
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let radix = match unsigned.get(..2).map(str::to_ascii_lowercase).as_deref() {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => return Number::parse(text).map(Value::from).or_else(|| Value::parse_quantity(text)),
+    };
+    let digits = &unsigned[2..];
+    // from_str_radix would accept a second sign after the prefix
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let magnitude = u64::from_str_radix(digits, radix).ok()?;
+    Some(match i64::try_from(magnitude) {
+        Ok(n) => Value::integer(if negative { -n } else { n }),
+        Err(_) => Value::from(if negative { -(magnitude as f64) } else { magnitude as f64 }),
+    })
+}
+
+// names that parse as numbers but can still be taken by operations
+fn is_special_literal(token: &str) -> bool {
+    let unsigned = token.strip_prefix(['+', '-']).unwrap_or(token).to_lowercase();
+    matches!(unsigned.as_str(), "inf" | "infinity" | "nan" | "eps")
+}
+
+// splits on whitespace, keeping vectors like `[1 2 3]` as one token
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+    let mut in_vector = false;
+    for word in text.split_whitespace() {
+        match tokens.last_mut() {
+            Some(last) if in_vector => {
+                last.push(' ');
+                last.push_str(word);
+            },
+            _ => tokens.push(word.into()),
+        }
+        in_vector = (in_vector || word.starts_with('[')) && !word.ends_with(']');
+    }
+    tokens
+}
+
+// digits with an optional sign and decimal point, like `-2.5`
+fn is_mantissa(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    digits.chars().any(|c| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && digits.matches('.').count() <= 1
+}
+
+// splits `3e8`, `3e-`, or `3e` into the mantissa and what's typed of the exponent
+fn split_exponent(word: &str) -> Option<(&str, &str)> {
+    let (mantissa, exponent) = word.split_once(['e', 'E'])?;
+    let digits = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+    (is_mantissa(mantissa) && digits.chars().all(|c| c.is_ascii_digit())).then_some((mantissa, exponent))
+}
+
+// a `#` on its own starts a comment that runs to the end of the line. one inside a word, like `a#b`, is left alone
+fn strip_comment(text: &str) -> &str {
+    text.char_indices()
+        .find(|&(i, c)| c == '#'
+            && text[..i].chars().next_back().is_none_or(char::is_whitespace)
+            && text[i + 1..].chars().next().is_none_or(char::is_whitespace))
+        .map_or(text, |(i, _)| &text[..i])
+}
+
+// splits `5*succ` or `succ;5` into the count and the operation. the count is only checked for being a number
+fn split_repeat(text: &str) -> Option<(&str, &str)> {
+    let is_count = |count: &str| {
+        let digits = count.strip_prefix('-').unwrap_or(count);
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    };
+    let (count, op) = text.split_once('*').filter(|&(count, _)| is_count(count))
+        .or_else(|| text.rsplit_once(';').filter(|&(_, count)| is_count(count)).map(|(op, count)| (count, op)))?;
+    (!op.is_empty()).then_some((count, op))
+}
+
+// the closest fraction to `n` with a denominator up to `limit`, as long as it's within a millionth of `n`
+fn closest_fraction(n: f64, limit: i64) -> Result<Rational, String> {
+    let close = |r: &Rational| (r.to_f64() - n).abs() <= FRACTION_TOLERANCE * n.abs();
+    Rational::approximate(n, limit).filter(close).ok_or_else(|| format!("No fraction with a denominator up to {limit} is close enough"))
+}
+
+// the command `text` names, and what it has after a colon. a command without an argument only matches its name
+fn find_command(text: &str) -> Option<(&'static Command, Option<&str>)> {
+    COMMANDS.iter().find_map(|command| match text.strip_prefix(command.name)? {
+        "" => Some((command, None)),
+        rest => rest.strip_prefix(':').filter(|_| command.argument).map(|argument| (command, Some(argument))),
+    })
+}
+
+// what Enter does
+fn submit(c: &mut Calculator, tx: Sender<Event>) {
+    if c.background && c.selected.is_none() && start_background(c, &tx) {
+        return;
+    }
+    let line = c.text_box.clone();
+    let before = c.stack.clone();
+    match c.eval_line(&line) {
+        Ok(()) => if let Some(index) = c.selected.take() {
+            c.place_edited(index, &before);
+        },
+        Err(e) => {
+            // the edited value is gone either way, and a later entry shouldn't land in its slot
+            c.selected = None;
+            // unwrap safety: rx lasts program lifetime
+            tx.send(Event::PushError(e)).unwrap();
+        },
+    }
+}
+
+// runs whatever is in the text box. see eval_line
+fn run_entry(c: &mut Calculator, tx: Sender<Event>) {
+    let before = c.stack.clone();
+    let entry = c.text_box.clone();
+    // switching isn't an edit of either stack, so it skips undo and the tape, but the script needs it to replay
+    if let Some(name) = entry.strip_prefix("ws:") {
+        // the entry itself is used up, so it isn't what's kept as this workspace's text box
+        c.text_box.clear();
+        match c.switch_workspace(name) {
+            Ok(()) => c.record_script(&before, &entry),
+            Err(e) => {
+                c.text_box = entry;
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(e.into())).unwrap();
+            },
+        }
+        return;
+    }
+    let mut succeeded = true;
+    if let Some(num) = c.parse_token(&c.text_box) {
+        c.stack.push(num);
+        c.last_push = Some((c.text_box.clone(), c.previous.clone(), c.stack.len()));
+        c.previous = mem::take(&mut c.text_box);
+    } else if let Some(definition) = c.text_box.strip_prefix("def ") {
+        let definition = definition.to_string();
+        match c.define(&definition) {
+            Ok(name) => {
+                c.text_box.clear();
+                if c.settings.save_definitions {
+                    if let Err(e) = c.save_definition(&definition) {
+                        // unwrap safety: rx lasts program lifetime
+                        tx.send(Event::PushError(format!("Defined {name}, but couldn't save it: {e}").into())).unwrap();
+                    }
+                }
+            },
+            // unwrap safety: rx lasts program lifetime
+            Err(e) => tx.send(Event::PushError(e.into())).unwrap(),
+        }
+    } else if let Some(name) = c.text_box.strip_prefix("show ").or_else(|| c.text_box.strip_prefix("def:")) {
+        match c.describe(name) {
+            Some(overlay) => {
+                c.overlay = Some(overlay);
+                c.text_box.clear();
+            },
+            // unwrap safety: rx lasts program lifetime
+            None => tx.send(Event::PushError(format!("No operation named {}", name.trim()).into())).unwrap(),
+        }
+    } else if c.text_box == "exportscript" || c.text_box.starts_with("exportscript ") {
+        let path = c.text_box.strip_prefix("exportscript ").map(str::trim).filter(|p| !p.is_empty()).map(String::from);
+        match c.export_script(path.as_deref()) {
+            Ok(path) => {
+                c.text_box.clear();
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(OpError::Notice(format!("Exported script to {}", path.display())))).unwrap();
+            },
+            // unwrap safety: rx lasts program lifetime
+            Err(e) => tx.send(Event::PushError(e.into())).unwrap(),
+        }
+    } else if c.text_box.trim().contains(char::is_whitespace) {
+        c.operate_line(tx.clone());
+    } else if c.text_box.is_empty() {
+        // the text box stays empty either way, so this is the only sign an empty entry failed
+        succeeded = match c.settings.empty_enter {
+            EmptyEnterAction::RepeatPrevious => c.operate_previous(tx.clone()),
+            EmptyEnterAction::Noop => true,
+            EmptyEnterAction::DupTop => match c.stack.last().cloned() {
+                Some(top) => {
+                    c.stack.push(top);
+                    true
+                },
+                None => {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError("Nothing to duplicate".into())).unwrap();
+                    false
+                },
+            },
+        };
+    } else if c.operate_from_input(tx.clone()) {
+        c.previous = mem::take(&mut c.text_box);
+    }
+    finish_entry(c, before, entry, succeeded, &tx);
+}
+
+// runs an entry that's a single Lua or Uiua operation on its own thread, so a slow one doesn't freeze the
+// screen. Event::OperationResult brings it back to finish_background. does nothing and returns false for
+// any other entry, or one that would fail straight away, so run_entry can handle it
+fn start_background(c: &mut Calculator, tx: &Sender<Event>) -> bool {
+    let entry = c.text_box.clone();
+    if entry.is_empty() || entry.contains(char::is_whitespace) || strip_comment(&entry) != entry || c.parse_token(&entry).is_some() {
+        return false;
+    }
+    // the same way dispatch gets there. aliases can't form cycles
+    let mut key = entry.to_lowercase();
+    while find_command(&key).is_none() {
+        match c.operations.get(&key) {
+            Some(Operation::Alias(target)) => key = target.clone(),
+            _ => break,
+        }
+    }
+    let Some(operation) = c.operations.get(&key).filter(|_| find_command(&key).is_none()) else { return false };
+    if operation.arity().is_some_and(|needed| c.stack.len() < needed) {
+        return false;
+    }
+    let taken = operation.taken(&c.stack);
+    let Some(Ok(call)) = c.prepare_call(&key) else { return false };
+    let operands = c.stack[c.stack.len() - taken..].to_vec();
+    // the interpreters go along with the call and come back with its result
+    let lua = mem::replace(&mut c.lua, Lua::new());
+    let mut uiua = mem::replace(&mut c.uiua, Calculator::fresh_uiua(c.operation_timeout));
+    let (shared, cancel, tx) = (c.lua_shared.clone(), c.cancel.clone(), tx.clone());
+    thread::spawn(move || {
+        let outcome = call.run(&lua, &shared, &mut uiua, &cancel);
+        // rx goes away if the user quit while this ran, and then there's nothing left to finish
+        let _ = tx.send(Event::OperationResult(Box::new(Finished { lua, uiua, outcome })));
+    });
+    c.running = Some(Running { entry, key, before: c.stack.clone(), operands });
+    true
+}
+
+// does what run_entry would have done after the operation, now that it's back
+fn finish_background(c: &mut Calculator, finished: Finished, tx: Sender<Event>) {
+    let Finished { lua, uiua, outcome } = finished;
+    c.lua = lua;
+    c.uiua = uiua;
+    // a stopped call is over, so the next one mustn't stop too
+    c.cancel.store(false, Ordering::Relaxed);
+    let Some(Running { entry, key, before, operands }) = c.running.take() else { return };
+    let (entry_tx, rx) = mpsc::channel();
+    let succeeded = c.apply_outcome(&key, outcome, &entry_tx);
+    if succeeded {
+        c.after_operation(&key, before.len(), operands, &entry_tx);
+        *c.usage.entry(entry.to_lowercase()).or_default() += 1;
+        c.previous = mem::take(&mut c.text_box);
+    }
+    finish_entry(c, before, entry, succeeded, &entry_tx);
+    if let Err(e) = c.entry_messages(&rx, false) {
+        // unwrap safety: rx lasts program lifetime
+        tx.send(Event::PushError(e)).unwrap();
+    }
+}
+
+// the end of every entry: the stack limit, the tape and script, undo, and history
+fn finish_entry(c: &mut Calculator, before: Vec<Value>, entry: String, mut succeeded: bool, tx: &Sender<Event>) {
+    if let Err(e) = c.limit_stack(&before, &entry) {
+        succeeded = false;
+        // unwrap safety: rx lasts program lifetime
+        tx.send(Event::PushError(e.into())).unwrap();
+    }
+    // an empty entry can repeat the last operation, which is what goes on the tape and in the script
+    if c.text_box.is_empty() && succeeded {
+        let input = match c.settings.empty_enter {
+            _ if !entry.is_empty() => entry.clone(),
+            EmptyEnterAction::DupTop => "dup".into(),
+            EmptyEnterAction::RepeatPrevious => c.previous.clone(),
+            EmptyEnterAction::Noop => String::new(),
+        };
+        if c.stack != before {
+            c.record_tape(&input);
+        }
+        // the script gets entries that leave the stack alone too, like `fmt`, but not exporting itself
+        if !input.is_empty() && !input.starts_with("exportscript") {
+            c.record_script(&before, &input);
+        }
+    }
+    c.record_undo(before);
+    // everything that succeeds clears the text box
+    if !entry.is_empty() && c.text_box.is_empty() {
+        c.history.push(entry);
+    }
+    c.history_index = c.history.len();
+}
+
+// evaluates each line of stdin, then prints the stack oldest first, one value per line.
+// errors go to stderr and the next line runs anyway; with `strict`, the first one stops everything and fails the run.
+// blank lines are skipped rather than repeating the last operation
+fn run_batch(c: &mut Calculator, strict: bool) -> bool {
+    for e in mem::take(&mut c.startup_errors) {
+        eprintln!("{e}");
+    }
+    for (i, line) in io::stdin().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Unable to read stdin: {e}");
+                return false;
+            },
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Err(e) = c.eval_line(&line) {
+            eprintln!("line {}: {e}", i + 1);
+            c.text_box.clear();
+            if strict {
+                return false;
+            }
+        }
+    }
+    for value in &c.stack {
+        println!("{}", c.format_value(value));
+    }
+    true
+}
+
+// polls instead of using OS notifications, which would mean the notify crate and a backend per platform
+// for two small files. only sends once a file has stopped changing
+fn watch_config(paths: Vec<PathBuf>, tx: Sender<Event>) {
+    let poll_rate = Duration::from_millis(250);
+    let debounce = Duration::from_millis(500);
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_seen: Vec<Option<SystemTime>> = paths.iter().map(|p| modified(p)).collect();
+    let mut pending: Vec<Option<Instant>> = vec![None; paths.len()];
+    loop {
+        thread::sleep(poll_rate);
+        for (i, path) in paths.iter().enumerate() {
+            let current = modified(path);
+            if current != last_seen[i] {
+                last_seen[i] = current;
+                pending[i] = Some(Instant::now());
+            } else if pending[i].is_some_and(|t| t.elapsed() >= debounce) {
+                pending[i] = None;
+                if tx.send(Event::ConfigChanged(path.clone())).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// the whole screen, drawn again after anything but a tick
+fn draw<B: Backend>(f: &mut Frame<B>, app: &Calculator) {
+    let window = f.size();
+    if window.width < MIN_WIDTH || window.height < MIN_HEIGHT {
+        let message = Paragraph::new("Terminal too small").style(app.theme.error).wrap(Wrap {trim: true});
+        f.render_widget(message, window);
+        return;
+    }
+    let stack_size = app.stack_pane(window);
+    let label_width = app.stack.len().saturating_sub(1).max(1).to_string().len();
+    let mut stack_lines = app.stack.iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let style = app.value_style(value);
+            let value = app.display_value(value);
+            let label = match app.settings.stack_labels {
+                StackLabels::Depth => format!("{:>label_width$}: ", app.stack.len() - 1 - i),
+                StackLabels::Position => format!("{:>label_width$}: ", i + 1),
+                StackLabels::None => String::new(),
+            };
+            if app.mode == Mode::StackEdit && app.selected == Some(i) {
+                let selected = Style::default().add_modifier(Modifier::REVERSED);
+                Spans::from(vec![Span::styled(label, selected), Span::styled(value, style.patch(selected))])
+            } else {
+                Spans::from(vec![Span::raw(label), Span::styled(value, style)])
+            }
+        })
+        .collect::<Vec<Spans>>();
+    if app.settings.growth_direction == GrowthDirection::Up {
+        stack_lines.reverse();
+    }
+    let scroll = app.stack_view(stack_size.height);
+    let stack = Paragraph::new(stack_lines)
+        .style(app.theme.text)
+        .scroll((scroll, 0))
+        .block(app.theme.block());
+    let box_size = Rect { height: 3, y: window.bottom().saturating_sub(4), ..window};
+    // the cursor underlines the character it's before, or is a `_` after the last one
+    let (before, after) = app.text_box.split_at(app.cursor_index());
+    let mut after = after.chars();
+    let mut text_spans = match after.next() {
+        Some(under) => vec![
+            Span::raw(before),
+            Span::styled(under.to_string(), Style::default().add_modifier(Modifier::UNDERLINED)),
+            Span::raw(after.as_str()),
+        ],
+        None => vec![Span::raw(format!("{before}_"))],
+    };
+    if let Some(hint) = app.exponent_hint() {
+        text_spans.push(Span::styled(format!("  {hint}"), Style::default().add_modifier(Modifier::DIM)));
+    }
+    if let Some(preview) = app.preview() {
+        text_spans.push(Span::styled(format!("  {preview}"), Style::default().add_modifier(Modifier::DIM)));
+    }
+    let text_box = Paragraph::new(Spans::from(text_spans))
+        .style(app.theme.text)
+        .block(app.theme.block());
+    f.render_widget(stack, stack_size);
+    // drawn over the right border, only when some values are scrolled out of view
+    if let Some((start, length)) = scrollbar_thumb(app.stack.len(), stack_size.height.saturating_sub(2), scroll) {
+        let thumb = Rect { x: stack_size.right() - 1, y: stack_size.y + 1 + start, width: 1, height: length };
+        let lines = vec![Spans::from("█"); length as usize];
+        f.render_widget(Paragraph::new(lines).style(app.theme.border), thumb);
+    }
+    if app.show_tape {
+        let tape_size = Rect { x: stack_size.width, width: window.width.saturating_sub(stack_size.width), ..stack_size };
+        let lines = app.tape.iter().map(|line| Spans::from(Span::raw(line))).collect::<Vec<Spans>>();
+        // newest at the bottom, scrolled so it's always in view
+        let scroll = (lines.len() as u16).saturating_sub(tape_size.height.saturating_sub(2));
+        let tape = Paragraph::new(lines)
+            .style(app.theme.text)
+            .scroll((scroll, 0))
+            .block(app.theme.block().title("Tape"));
+        f.render_widget(tape, tape_size);
+    }
+    f.render_widget(text_box, box_size);
+
+    let (word, matches, selected) = app.suggestions();
+    // nothing to suggest once what's typed is the only match
+    if !matches.is_empty() && matches != [word.to_lowercase()] {
+        // five at a time, keeping the selected one in view
+        let first = selected.unwrap_or(0).saturating_sub(4);
+        let shown: Vec<String> = matches[first..matches.len().min(first + 5)].iter()
+            .map(|name| format!("{name} {}", app.resolve(name).map_or("(varies)".into(), Operation::hint)))
+            .collect();
+        let width = shown.iter().map(|line| line.chars().count()).max().unwrap_or(0) as u16 + 2;
+        let popup = Rect::new(1, box_size.y.saturating_sub(shown.len() as u16 + 2), width.min(window.width.saturating_sub(2)), shown.len() as u16 + 2);
+        let lines = shown.iter().enumerate().map(|(i, line)| match selected {
+            Some(selected) if selected == first + i => Spans::from(Span::styled(line.as_str(), Style::default().add_modifier(Modifier::REVERSED))),
+            _ => Spans::from(Span::raw(line.as_str())),
+        }).collect::<Vec<Spans>>();
+        f.render_widget(Clear, popup);
+        f.render_widget(Paragraph::new(lines).block(app.theme.block()), popup);
+    }
+
+    let status_size = Rect { height: 1, y: window.bottom().saturating_sub(1), ..window };
+    let mode = if app.mode == Mode::StackEdit { "  EDIT (Enter edits, Delete drops)" } else { "" };
+    let status = format!(" {}  depth {}  {}  {}  {}{mode}", app.workspace, app.stack.len(), app.angle_mode.name(), app.display_format.name(), app.output_base.name());
+    f.render_widget(Paragraph::new(status), status_size);
+    if let Some(running) = &app.running {
+        let running = Paragraph::new(format!("running {}, Esc stops it ", running.entry)).alignment(Alignment::Right);
+        f.render_widget(running, status_size);
+    } else if app.armed_reset.is_some() {
+        let confirm = Paragraph::new("reset again to clear the stack ").style(app.theme.error).alignment(Alignment::Right);
+        f.render_widget(confirm, status_size);
+    } else if !app.previous.is_empty() {
+        let previous = Paragraph::new(format!("last: {} ", app.previous)).alignment(Alignment::Right);
+        f.render_widget(previous, status_size);
+    }
+
+    if let Some(mut error_box) = app.settings.layout.error_area(stack_size) {
+        if !app.startup_errors.is_empty() {
+            // tall enough for every wrapped line, and the errors below get what's left
+            let inner_width = error_box.width.saturating_sub(2).max(1) as usize;
+            let lines: usize = app.startup_errors.iter().map(|e| e.chars().count().div_ceil(inner_width).max(1)).sum();
+            let panel = Rect { height: (lines as u16 + 2).min(error_box.height), ..error_box };
+            let problems = Paragraph::new(app.startup_errors.iter().map(|e| Spans::from(Span::raw(e))).collect::<Vec<Spans>>())
+                .style(app.theme.error)
+                .wrap(Wrap {trim: true})
+                .block(app.theme.block().title("Startup problems (Esc)"));
+            f.render_widget(Clear, panel);
+            f.render_widget(problems, panel);
+            error_box.y += panel.height;
+            error_box.height -= panel.height;
+        }
+        let error = Paragraph::new(app.errors.iter().map(|(e, _)| Spans::from(Span::styled(e.to_string(), e.style(&app.theme)))).collect::<Vec<Spans>>())
+            .style(app.theme.error)
+            .wrap(Wrap {trim: true});
+        match app.settings.layout.error_panel {
+            ErrorPanel::Hidden => {},
+            // the full width would cover whole rows of values, so they're cleared rather than mixed in
+            ErrorPanel::Bottom if !app.errors.is_empty() => {
+                f.render_widget(Clear, error_box);
+                f.render_widget(error, error_box);
+            },
+            _ => f.render_widget(error, error_box),
+        }
+    }
+
+    if app.show_log {
+        let log_box = Rect::new(2, window.y + 1, window.width.saturating_sub(4), stack_size.height.saturating_sub(2));
+        let lines = app.log.iter()
+            .map(|(time, message)| Spans::from(Span::raw(format!("{:>8.1}s  {message}", (*time - app.started).as_secs_f64()))))
+            .collect::<Vec<Spans>>();
+        // newest at the bottom, scrolled so it's always in view
+        let scroll = (lines.len() as u16).saturating_sub(log_box.height.saturating_sub(2));
+        let contents = Paragraph::new(lines)
+            .scroll((scroll, 0))
+            .block(app.theme.block().title("Log"));
+        f.render_widget(Clear, log_box);
+        f.render_widget(contents, log_box);
+    }
+
+    if let Some(overlay) = &app.overlay {
+        let overlay_box = Rect::new(2, window.y + 1, window.width.saturating_sub(4), stack_size.height.saturating_sub(2));
+        let contents = Paragraph::new(overlay.lines.iter().map(String::as_str).map(Span::raw).map(Spans::from).collect::<Vec<Spans>>())
+            .scroll((overlay.scroll, 0))
+            .block(app.theme.block().title(overlay.title.as_str()));
+        f.render_widget(Clear, overlay_box);
+        f.render_widget(contents, overlay_box);
+    }
+}
+
+// where --inline draws: the rows from `top` down, moved up if there isn't room under it
+fn inline_area(top: u16) -> io::Result<Rect> {
+    let (width, rows) = terminal::size()?;
+    let height = INLINE_HEIGHT.min(rows);
+    Ok(Rect { x: 0, y: top.min(rows - height), width, height })
+}
+
+fn main() -> Result<(), Box<dyn Error>>{
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // on its own calculator, before any settings or configs could change the results
+    if args.iter().any(|a| a == "--selftest") {
+        let passed = selftest::run(args.iter().any(|a| a == "--machine-readable"));
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    // draws under the shell prompt and leaves the last frame in the scrollback
+    let inline = args.iter().any(|a| a == "--inline");
+
+    // a panic anywhere, including the keyboard thread, would otherwise leave the shell in raw mode
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if is_raw_mode_enabled().unwrap_or(true) {
+            let _ = disable_raw_mode();
+            if !inline {
+                let _ = execute!(io::stdout(), LeaveAlternateScreen);
+            }
+            let _ = execute!(io::stdout(), DisableBracketedPaste, DisableMouseCapture);
+        }
+        default_hook(info);
+    }));
+
+    let mut paths = Paths::resolve();
+    let lua_config = paths.as_ref().map(|p| p.lua_config.clone());
+    let uiua_config = paths.as_ref().map(|p| p.uiua_config.clone());
+
+    let mut app = Calculator::new();
+    let (tx, rx) = mpsc::channel();
+    let mut keybinds = keybinds::defaults();
+
+    if let Some(paths) = &mut paths {
+        if let Err(e) = paths.ensure_writable() {
+            app.push_startup_error(e);
+        }
+        app.data_dir = paths.data_dir.clone();
+        match Settings::load(&paths.settings) {
+            Ok(settings) => {
+                app.error_timeout = settings.error_timeout();
+                app.operation_timeout = settings.operation_timeout();
+                app.uiua = Calculator::fresh_uiua(app.operation_timeout);
+                app.theme = Theme::from_config(&settings.theme);
+                app.epsilon.set(settings.equality_epsilon.max(0.0));
+                app.empty_total.set(settings.empty_total);
+                app.settings = settings;
+            },
+            Err(e) => app.push_startup_error(e),
+        }
+        let errors;
+        (keybinds, errors) = keybinds::load(&paths.keybinds);
+        for e in errors {
+            app.push_startup_error(e);
+        }
+    } else {
+        app.push_startup_error("Failed to locate the config directory".into());
+    }
+
+    // load lua
+    if let Err(e) = app.load_lua(BASE_LUA) {
+        panic!("Error in the base lua config file! {e}");
+    }
+    // not having a config is normal for anyone who doesn't script, so it's only mentioned with --verbose
+    let verbose = args.iter().any(|a| a == "--verbose");
+    if let Some(lua_config) = lua_config.clone() {
+        if !lua_config.exists() {
+            if verbose {
+                app.push_startup_error(format!("No Lua config at {}", lua_config.display()));
+            }
+        } else if let Err(e) = app.load_lua(lua_config) {
+            app.push_startup_error(format!("Unable to load Lua config: {e}"));
+        }
+    }
+    if let Some(uiua_config) = uiua_config.clone() {
+        if !uiua_config.exists() {
+            if verbose {
+                app.push_startup_error(format!("No Uiua config at {}", uiua_config.display()));
+            }
+        } else if let Err(e) = app.load_uiua(uiua_config) {
+            app.push_startup_error(format!("Unable to load Uiua config: {e}"));
+        }
+    }
+    // after the configs, since definitions can use their operations
+    for e in app.load_definitions() {
+        app.push_startup_error(format!("Unable to load saved definition: {e}"));
+    }
+    if let Some(paths) = &paths {
+        for e in app.load_aliases(&paths.aliases) {
+            app.push_startup_error(e);
+        }
+    }
+    // configs are loaded so custom functions work, but the session is left alone
+    if args.iter().any(|a| a == "--batch") {
+        let passed = run_batch(&mut app, args.iter().any(|a| a == "--strict"));
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    // a session that wasn't restored isn't saved either, so the one from before is still there next time
+    let restore = !args.iter().any(|a| a == "--no-restore");
+    if restore {
+        if let Err(e) = app.restore_session() {
+            app.push_startup_error(e);
+        }
+    }
+    if let Err(e) = app.load_usage() {
+        app.push_startup_error(e);
+    }
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    if !inline {
+        execute!(stdout, EnterAlternateScreen)?;
+    }
+    execute!(stdout, EnableBracketedPaste)?;
+    // capturing the mouse stops the terminal's own text selection, so it can be turned off
+    if app.settings.mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
+
+    let terminal = if inline {
+        // near the bottom of the screen, the shell's output scrolls up to make room
+        let (_, row) = cursor::position()?;
+        let area = inline_area(row)?;
+        if area.y < row {
+            execute!(stdout, ScrollUp(row - area.y))?;
+        }
+        execute!(stdout, MoveTo(0, area.y), terminal::Clear(ClearType::FromCursorDown))?;
+        Terminal::with_options(CrosstermBackend::new(stdout), TerminalOptions { viewport: Viewport::fixed(area) })
+    } else {
+        Terminal::new(CrosstermBackend::new(stdout))
+    };
+    let mut terminal = terminal?;
+
+    if app.settings.watch_config {
+        let watcher_tx = tx.clone();
+        let paths = lua_config.iter().chain(uiua_config.iter()).cloned().collect();
+        thread::spawn(move || watch_config(paths, watcher_tx));
+    }
+
+    let keyboard_tx = tx.clone();
+    let tick_rate = Duration::from_millis(app.settings.tick_rate_ms.max(1));
+
+    thread::spawn(move || {
+        let tx = keyboard_tx;
+        let mut last_tick = Instant::now();
+        loop {
+            // Timeout is duration until next tick
+            let timeout = tick_rate
+                .checked_sub(Instant::now() - last_tick)
+                .unwrap_or(Duration::from_secs(0));
+            // Wait for events within that duration and send them over the mpsc channel
+            // unwrap safety: fatal
+            if event::poll(timeout).unwrap() {
+            // unwrap safety: fatal
+                let event = match event::read().unwrap() {
+                    CEvent::Key(key) => keybinds::lookup(&keybinds, key).map_or(Event::Input(key), Action::event),
+                    CEvent::Paste(text) => Event::PasteText(text),
+                    CEvent::Resize(..) => Event::Resize,
+                    CEvent::Mouse(mouse) => match mouse.kind {
+                        MouseEventKind::Down(MouseButton::Left) => Event::Click(mouse.column, mouse.row),
+                        MouseEventKind::ScrollUp => Event::WheelUp,
+                        MouseEventKind::ScrollDown => Event::WheelDown,
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+                // unwrap safety: rx lasts program lifetime
+                tx.send(event).unwrap();
+            }
+            // If no inputs received during that time, send a tick event
+            if (Instant::now() - last_tick) >= tick_rate && tx.send(Event::Tick).is_ok() {
+                last_tick = Instant::now();
+            }
+        }
+    });
+
+    // only ticks leave the screen as it was, so anything else means drawing again
+    let mut dirty = true;
+    // what arrived while a call ran in the background, handled in order once it's done
+    let mut deferred = VecDeque::new();
+    app.background = true;
+    loop {
+        app.clamp_selection();
+        if dirty {
+            // Draw
+            terminal.draw(|f| draw(f, &app))?;
+        }
+
+        // Handle events
+        let queued = if app.running.is_none() { deferred.pop_front() } else { None };
+        let event = queued.unwrap_or_else(|| rx.recv().unwrap());
+        // the calculator is missing its interpreters until the call is back, so input waits. Esc stops a Lua call
+        if app.running.is_some() {
+            match event {
+                Event::OperationResult(_) | Event::Tick | Event::Resize | Event::PushError(_) | Event::Quit => {},
+                Event::PopError => {
+                    app.cancel.store(true, Ordering::Relaxed);
+                    continue;
+                },
+                event => {
+                    deferred.push_back(event);
+                    continue;
+                },
+            }
+        }
+        dirty = !matches!(event, Event::Tick);
+        // anything else the user does calls off an armed reset
+        if !matches!(event, Event::Reset | Event::Tick | Event::Resize | Event::PushError(_) | Event::ConfigChanged(_)) {
+            app.armed_reset = None;
+        }
+        // anything but editing puts the cursor back at the end, where history and pasting work
+        if !matches!(event, Event::Input(_) | Event::MoveCursor(_) | Event::DeleteForward | Event::Exponent | Event::Complete | Event::Tick | Event::Resize | Event::PushError(_) | Event::ConfigChanged(_)) {
+            app.cursor_back = 0;
+        }
+        if app.overlay.is_some() && matches!(event, Event::Input(_) | Event::Submit) {
+            app.overlay = None;
+            continue;
+        }
+        if app.mode == Mode::StackEdit {
+            let rows = terminal.get_frame().size().height.saturating_sub(6).max(1);
+            let handled = match event {
+                // up and down move the way they point on screen
+                Event::HistoryPrev | Event::HistoryNext => {
+                    let up = matches!(event, Event::HistoryPrev);
+                    app.move_selection(up == (app.settings.growth_direction == GrowthDirection::Down), rows);
+                    true
+                },
+                Event::Input(KeyEvent {code: KeyCode::Delete | KeyCode::Backspace, ..}) | Event::DeleteForward => {
+                    app.delete_selected();
+                    true
+                },
+                Event::Submit => {
+                    app.edit_selected();
+                    true
+                },
+                Event::PopError | Event::ToggleStackEdit => {
+                    app.leave_stack_edit();
+                    true
+                },
+                // typing waits until the mode is left
+                Event::Input(_) | Event::MoveCursor(_) => true,
+                _ => false,
+            };
+            if handled {
+                continue;
+            }
+        }
+        match event {
+            Event::Quit => break,
+            Event::Input(KeyEvent {code: KeyCode::Backspace, ..}) => {
+                if app.text_box.is_empty() && app.settings.backspace_pops_stack {
+                    let before = app.stack.clone();
+                    app.stack.pop();
+                    app.record_undo(before);
+                } else {
+                    app.delete_back();
+                }
+            },
+            Event::Input(KeyEvent {code: KeyCode::Char(chr), ..}) => {
+                app.insert_char(chr);
+                app.history_index = app.history.len();
+            },
+            Event::MoveCursor(to) => { app.move_cursor(to); },
+            Event::DeleteForward => { app.delete_forward(); },
+            Event::Submit => { submit(&mut app, tx.clone()); },
+            Event::OperationResult(finished) => { finish_background(&mut app, *finished, tx.clone()); },
+            Event::Reset => { app.request_reset(Instant::now()); },
+            Event::ToggleLog => { app.show_log = !app.show_log; },
+            Event::ToggleTape => { app.show_tape = !app.show_tape; },
+            Event::Exponent => if let Err(e) = app.enter_exponent() {
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(e.into())).unwrap();
+            },
+            Event::NextWorkspace => {
+                let switched = app.next_workspace()
+                    .ok_or_else(|| "No other workspaces yet. Type ws:<name> to start one".to_string())
+                    .and_then(|name| app.switch_workspace(&name));
+                if let Err(e) = switched {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(e.into())).unwrap();
+                }
+            },
+            Event::PopToEntry => if let Err(e) = app.pop_to_entry() {
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(e.into())).unwrap();
+            },
+            Event::Inspect => if let Err(e) = app.inspect() {
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(e.into())).unwrap();
+            },
+            Event::ToggleStackEdit => if let Err(e) = app.toggle_stack_edit() {
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(e.into())).unwrap();
+            },
+            Event::SaveTape => {
+                let message = match app.save_tape() {
+                    Ok(path) => OpError::Notice(format!("Saved tape to {}", path.display())),
+                    Err(e) => e.into(),
+                };
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(message)).unwrap();
+            },
+            Event::Help => { app.overlay = Some(app.help()); },
+            Event::Complete => { app.complete(); },
+            Event::Paste | Event::PasteText(_) => {
+                let text = match event {
+                    Event::PasteText(text) => Ok(text),
+                    _ => clipboard::read(),
+                };
+                if let Err(e) = text.and_then(|text| app.paste(&text)) {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(e.into())).unwrap();
+                }
+            },
+            Event::Copy => {
+                let message = match app.copy_top() {
+                    Ok(top) => OpError::Notice(format!("Copied {top}")),
+                    Err(e) => e.into(),
+                };
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(message)).unwrap();
+            },
+            Event::Undo => { app.undo(); },
+            Event::Redo => { app.redo(); },
+            Event::HistoryPrev => { app.history_prev(); },
+            Event::HistoryNext => { app.history_next(); },
+            // PageUp always moves toward older values, whichever way the stack grows
+            Event::ScrollUp | Event::ScrollDown | Event::WheelUp | Event::WheelDown if app.overlay.is_some() => {
+                let page = terminal.get_frame().size().height.saturating_sub(8).max(1);
+                let amount = if matches!(event, Event::WheelUp | Event::WheelDown) { WHEEL_LINES } else { page };
+                // unwrap safety: matched on overlay being Some
+                let overlay = app.overlay.as_mut().unwrap();
+                let max_scroll = (overlay.lines.len() as u16).saturating_sub(page);
+                overlay.scroll = match event {
+                    Event::ScrollUp | Event::WheelUp => overlay.scroll.saturating_sub(amount),
+                    _ => overlay.scroll.saturating_add(amount).min(max_scroll),
+                };
+            },
+            Event::ScrollUp | Event::ScrollDown | Event::WheelUp | Event::WheelDown => {
+                let page = terminal.get_frame().size().height.saturating_sub(6).max(1);
+                let max_scroll = (app.stack.len() as u16).saturating_sub(page);
+                // the wheel moves the way it points on screen, so toward older values only if they're above
+                let older = match event {
+                    Event::ScrollUp => true,
+                    Event::ScrollDown => false,
+                    Event::WheelUp => app.settings.growth_direction == GrowthDirection::Down,
+                    _ => app.settings.growth_direction == GrowthDirection::Up,
+                };
+                let amount = if matches!(event, Event::WheelUp | Event::WheelDown) { WHEEL_LINES } else { page };
+                app.stack_scroll = if older {
+                    app.stack_scroll.saturating_add(amount).min(max_scroll)
+                } else {
+                    app.stack_scroll.saturating_sub(amount)
+                };
+            },
+            // clicks outside the stack pane, like in the text box, do nothing
+            Event::Click(column, row) => {
+                if app.overlay.is_none() && !app.show_log {
+                    if let Some(index) = app.stack_index_at(terminal.get_frame().size(), column, row) {
+                        app.stack_click(index);
+                    }
+                }
+            },
+            Event::ClearTextBox => {
+                mem::take(&mut app.text_box);
+                app.selected = None;
+            },
+            // expired errors and reset confirmations are the only things a tick changes
+            Event::Tick => {
+                let now = Instant::now();
+                let expired = app.expire_errors(now) > 0;
+                dirty = app.expire_reset(now) || expired;
+            },
+            // the inline area keeps its place and fits the new size. resizing clears the whole screen
+            Event::Resize if inline => {
+                let top = terminal.get_frame().size().y;
+                terminal.resize(inline_area(top)?)?;
+            },
+            Event::Resize | Event::Input(..) => {},
+            Event::PushError(e) => { app.push_error(e); },
+            // the startup panel goes first, since it stays until dismissed
+            Event::PopError => if app.startup_errors.is_empty() {
+                app.errors.pop_front();
+            } else {
+                app.startup_errors.clear();
+            },
+            Event::Reload => {
+                let mut messages = vec![];
+                // Uiua first, so Lua functions it no longer shadows come back. a missing file still
+                // unloads the old functions, but isn't a problem
+                if let Some(path) = &uiua_config {
+                    if let Err(e) = app.reload_uiua(path) {
+                        if path.exists() {
+                            messages.push(format!("Unable to reload Uiua config: {e}").into());
+                        }
+                    }
+                }
+                if let Some(path) = &lua_config {
+                    if let Err(e) = app.reload_lua(path.clone()) {
+                        if path.exists() {
+                            messages.push(format!("Unable to reload Lua config: {e}").into());
+                        }
+                    }
+                }
+                if messages.is_empty() {
+                    messages.push(OpError::Notice("Reloaded configs".into()));
+                }
+                for message in messages {
+                    // unwrap safety: rx lasts program lifetime
+                    tx.send(Event::PushError(message)).unwrap();
+                }
+            },
+            Event::ConfigChanged(path) => {
+                let result = if Some(&path) == lua_config.as_ref() {
+                    app.reload_lua(path.clone()).map_err(|e| e.to_string())
+                } else {
+                    app.reload_uiua(&path).map_err(|e| e.to_string())
+                };
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let message = match result {
+                    Ok(()) => OpError::Notice(format!("Reloaded {name}")),
+                    Err(_) if !path.exists() => OpError::Notice(format!("Unloaded {name}, which was removed")),
+                    Err(e) => format!("Unable to reload {name}: {e}").into(),
+                };
+                // unwrap safety: rx lasts program lifetime
+                tx.send(Event::PushError(message)).unwrap();
+            },
+        }
+    }
+
+    disable_raw_mode()?;
+    if inline {
+        // the last frame stays where it was, with the shell prompt on the line under it
+        let area = terminal.get_frame().size();
+        execute!(terminal.backend_mut(), MoveTo(0, area.bottom().saturating_sub(1)), Show, DisableBracketedPaste, DisableMouseCapture)?;
+        println!();
+    } else {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableBracketedPaste, DisableMouseCapture)?;
+    }
+    // after leaving the TUI so the error is still visible
+    if restore {
+        if let Err(e) = app.save_session() {
+            eprintln!("{e}");
+        }
+    }
+    if let Err(e) = app.save_usage() {
+        eprintln!("{e}");
+    }
     Ok(())
 }
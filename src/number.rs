@@ -0,0 +1,332 @@
+use std::fmt;
+
+// always in lowest terms, with a positive denominator. hand-rolled i64 fractions rather than
+// num-rational's BigRational; results that don't fit fall back to Float, which operate warns about
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rational {
+    numer: i64,
+    denom: i64,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Number {
+    Rational(Rational),
+    Float(f64),
+    // real and imaginary parts; the imaginary part is never zero. a variant rather than num_complex's
+    // Complex64 so real values keep their Rational/Float distinction
+    Complex(f64, f64),
+}
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.abs()
+}
+
+impl Rational {
+    // None if the denominator is zero or the reduced value doesn't fit in an i64
+    fn new(numer: i128, denom: i128) -> Option<Self> {
+        if denom == 0 {
+            return None;
+        }
+        let divisor = gcd(numer, denom) * denom.signum();
+        Some(Self {
+            numer: (numer / divisor).try_into().ok()?,
+            denom: (denom / divisor).try_into().ok()?,
+        })
+    }
+
+    pub fn integer(n: i64) -> Self {
+        Self { numer: n, denom: 1 }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    // the fraction closest to `x` with a denominator no bigger than `max_denom`, from the continued fraction
+    // of x. the last step can be a semiconvergent, which is sometimes closer than any convergent that fits
+    pub fn approximate(x: f64, max_denom: i64) -> Option<Self> {
+        if !x.is_finite() || x.abs() >= 2f64.powi(62) || max_denom < 1 {
+            return None;
+        }
+        let max_denom = i128::from(max_denom);
+        // the two convergents before the current one
+        let (mut h0, mut h1, mut k0, mut k1) = (0i128, 1i128, 1i128, 0i128);
+        let mut rest = x;
+        loop {
+            let a = rest.floor();
+            let (h2, k2) = (a as i128 * h1 + h0, a as i128 * k1 + k0);
+            if k2 > max_denom {
+                let t = (max_denom - k0) / k1;
+                let (hs, ks) = (t * h1 + h0, t * k1 + k0);
+                let distance = |h: i128, k: i128| (h as f64 / k as f64 - x).abs();
+                if t > 0 && distance(hs, ks) < distance(h1, k1) {
+                    return Self::new(hs, ks);
+                }
+                break;
+            }
+            (h0, h1, k0, k1) = (h1, h2, k1, k2);
+            let fraction = rest - a;
+            if fraction == 0.0 || h1 as f64 / k1 as f64 == x {
+                break;
+            }
+            rest = 1.0 / fraction;
+        }
+        Self::new(h1, k1)
+    }
+
+    fn parts(self) -> (i128, i128) {
+        (self.numer.into(), self.denom.into())
+    }
+
+    // each of these returns None on overflow or division by zero, so the caller can fall back to floats
+    fn add(self, other: Self) -> Option<Self> {
+        let ((a, b), (c, d)) = (self.parts(), other.parts());
+        Self::new((a * d).checked_add(c * b)?, b * d)
+    }
+
+    fn sub(self, other: Self) -> Option<Self> {
+        let ((a, b), (c, d)) = (self.parts(), other.parts());
+        Self::new((a * d).checked_sub(c * b)?, b * d)
+    }
+
+    fn mul(self, other: Self) -> Option<Self> {
+        let ((a, b), (c, d)) = (self.parts(), other.parts());
+        Self::new(a * c, b * d)
+    }
+
+    fn div(self, other: Self) -> Option<Self> {
+        let ((a, b), (c, d)) = (self.parts(), other.parts());
+        Self::new(a * d, b * c)
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denom == 1 {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+impl From<f64> for Number {
+    fn from(n: f64) -> Self {
+        Self::Float(n)
+    }
+}
+
+impl Number {
+    pub fn integer(n: i64) -> Self {
+        Self::Rational(Rational::integer(n))
+    }
+
+    // a complex number with no imaginary part is just a float
+    pub fn complex(re: f64, im: f64) -> Self {
+        if im == 0.0 {
+            Self::Float(re)
+        } else {
+            Self::Complex(re, im)
+        }
+    }
+
+    // integers and `a/b` are exact, `a+bi` is complex, anything else is a float
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Ok(n) = text.parse::<i64>() {
+            return Some(Self::integer(n));
+        }
+        if let Some((numer, denom)) = text.split_once('/') {
+            let numer = numer.parse::<i64>().ok()?;
+            // a sign on the denominator would make `1/-2` valid but `1/+-2` confusing
+            if denom.starts_with(['+', '-']) {
+                return None;
+            }
+            return Rational::new(numer.into(), denom.parse::<i64>().ok()?.into()).map(Self::Rational);
+        }
+        // a digit has to come first, so `i` and names ending in it are left for operations
+        if let Some(complex) = text.strip_suffix('i').filter(|c| c.contains(|c: char| c.is_ascii_digit())) {
+            return Self::parse_complex(complex);
+        }
+        text.parse().ok().map(Self::Float)
+    }
+
+    // takes `a+b`, `a-b`, or `b` from `a+bi`, `a-bi`, or `bi`
+    fn parse_complex(text: &str) -> Option<Self> {
+        // the sign between the parts is the last one that isn't leading or part of an exponent
+        let split = text.char_indices()
+            .rfind(|&(i, c)| (c == '+' || c == '-') && i > 0 && !text[..i].ends_with(['e', 'E']))
+            .map(|(i, _)| i);
+        let (re, im) = match split {
+            Some(i) => (text[..i].parse::<f64>().ok()?, &text[i..]),
+            None => (0.0, text),
+        };
+        // `i` and `-i` have an implied 1
+        let im = match im {
+            "" | "+" => 1.0,
+            "-" => -1.0,
+            im => im.parse::<f64>().ok()?,
+        };
+        // parsing `inf` here would make names like `fini` into numbers
+        (re.is_finite() && im.is_finite()).then(|| Self::complex(re, im))
+    }
+
+    // parses back to exactly the same value
+    pub fn to_literal(self) -> String {
+        match self {
+            Self::Rational(r) => r.to_string(),
+            Self::Float(n) => format!("{n:?}"),
+            Self::Complex(re, im) => format!("{re:?}{im:+?}i"),
+        }
+    }
+
+    // NaN for complex numbers, so they fail any range check on the result
+    pub fn to_f64(self) -> f64 {
+        self.as_real().unwrap_or(f64::NAN)
+    }
+
+    pub fn as_real(self) -> Option<f64> {
+        match self {
+            Self::Rational(r) => Some(r.to_f64()),
+            Self::Float(n) => Some(n),
+            Self::Complex(..) => None,
+        }
+    }
+
+    pub fn is_finite(self) -> bool {
+        match self {
+            Self::Rational(_) => true,
+            Self::Float(n) => n.is_finite(),
+            Self::Complex(re, im) => re.is_finite() && im.is_finite(),
+        }
+    }
+
+    fn parts(self) -> (f64, f64) {
+        match self {
+            Self::Complex(re, im) => (re, im),
+            n => (n.to_f64(), 0.0),
+        }
+    }
+
+    fn combine(
+        self,
+        other: Self,
+        exact: impl Fn(Rational, Rational) -> Option<Rational>,
+        float: impl Fn(f64, f64) -> f64,
+        complex: impl Fn((f64, f64), (f64, f64)) -> (f64, f64),
+    ) -> Self {
+        match (self, other) {
+            (Self::Rational(a), Self::Rational(b)) => exact(a, b)
+                .map(Self::Rational)
+                .unwrap_or_else(|| Self::Float(float(a.to_f64(), b.to_f64()))),
+            (Self::Complex(..), _) | (_, Self::Complex(..)) => {
+                let (re, im) = complex(self.parts(), other.parts());
+                Self::complex(re, im)
+            },
+            _ => Self::Float(float(self.to_f64(), other.to_f64())),
+        }
+    }
+
+    pub fn add(self, other: Self) -> Self {
+        self.combine(other, Rational::add, |a, b| a + b, |(a, b), (c, d)| (a + c, b + d))
+    }
+
+    pub fn sub(self, other: Self) -> Self {
+        self.combine(other, Rational::sub, |a, b| a - b, |(a, b), (c, d)| (a - c, b - d))
+    }
+
+    pub fn mul(self, other: Self) -> Self {
+        self.combine(other, Rational::mul, |a, b| a * b, |(a, b), (c, d)| (a * c - b * d, a * d + b * c))
+    }
+
+    pub fn div(self, other: Self) -> Self {
+        self.combine(other, Rational::div, |a, b| a / b, |(a, b), (c, d)| {
+            let denom = c * c + d * d;
+            ((a * c + b * d) / denom, (b * c - a * d) / denom)
+        })
+    }
+
+    // complex numbers give their magnitude
+    pub fn abs(self) -> Self {
+        match self {
+            Self::Rational(r) => match r.numer.checked_abs() {
+                Some(numer) => Self::Rational(Rational { numer, ..r }),
+                None => Self::Float(r.to_f64().abs()),
+            },
+            Self::Float(n) => Self::Float(n.abs()),
+            Self::Complex(re, im) => Self::Float(re.hypot(im)),
+        }
+    }
+
+    // negative reals have imaginary roots
+    pub fn sqrt(self) -> Self {
+        match self {
+            Self::Complex(re, im) => {
+                let modulus = re.hypot(im);
+                let root_re = ((modulus + re) / 2.0).sqrt();
+                let root_im = ((modulus - re) / 2.0).sqrt().copysign(im);
+                Self::complex(root_re, root_im)
+            },
+            n if n.to_f64() < 0.0 => Self::complex(0.0, (-n.to_f64()).sqrt()),
+            n => Self::Float(n.to_f64().sqrt()),
+        }
+    }
+
+    // only goes through complex logarithms if one side is already complex
+    pub fn pow(self, other: Self) -> Self {
+        self.combine(other, |_, _| None, f64::powf, |(a, b), (c, d)| {
+            if a == 0.0 && b == 0.0 {
+                return (0.0, 0.0);
+            }
+            let (ln_re, ln_im) = (a.hypot(b).ln(), b.atan2(a));
+            let (exp_re, exp_im) = (c * ln_re - d * ln_im, c * ln_im + d * ln_re);
+            let scale = exp_re.exp();
+            (scale * exp_im.cos(), scale * exp_im.sin())
+        })
+    }
+}
+
+// rounds to `digits` significant digits, so `0.30000000000000004` with 15 becomes `0.3`.
+// 0 digits means no rounding. zero, infinities, and NaN come back unchanged
+pub fn round_to_significant(n: f64, digits: u32) -> f64 {
+    if digits == 0 || n == 0.0 || !n.is_finite() {
+        return n;
+    }
+    let digits = digits.min(17) as usize;
+    // going through decimal text rounds correctly where scaling by a power of ten wouldn't
+    format!("{n:.*e}", digits - 1).parse().unwrap_or(n)
+}
+
+// rounds to `places` digits after the decimal point. infinities and NaN come back unchanged
+pub fn round_to_places(n: f64, places: u32) -> f64 {
+    if !n.is_finite() {
+        return n;
+    }
+    // past this many places every float is already exact. like round_to_significant, this goes
+    // through decimal text, since scaling by a huge power of ten overflows
+    let places = places.min(1100) as usize;
+    format!("{n:.places$}").parse().unwrap_or(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{round_to_significant, Number};
+
+    #[test]
+    fn significant_digits() {
+        assert_eq!(round_to_significant(0.1 + 0.2, 15), 0.3);
+        assert_eq!(round_to_significant(-123_456.0, 2), -120_000.0);
+        assert_eq!(round_to_significant(0.1 + 0.2, 0), 0.1 + 0.2);
+    }
+
+    // complex literals need a digit, so `i` on its own is left for an operation
+    #[test]
+    fn complex_needs_digit() {
+        assert_eq!(Number::parse("2i"), Some(Number::complex(0.0, 2.0)));
+        assert_eq!(Number::parse("3-i"), Some(Number::complex(3.0, -1.0)));
+        assert_eq!(Number::parse("i"), None);
+        assert_eq!(Number::parse("-i"), None);
+    }
+}
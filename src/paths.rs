@@ -0,0 +1,44 @@
+use directories::ProjectDirs;
+
+use std::{fs, path::PathBuf};
+
+pub struct Paths {
+    pub lua_config: PathBuf,
+    pub uiua_config: PathBuf,
+    pub settings: PathBuf,
+    pub keybinds: PathBuf,
+    pub aliases: PathBuf,
+    // None if the data directory can't be written to; anything that persists state checks this
+    pub data_dir: Option<PathBuf>,
+}
+
+impl Paths {
+    // returns None if no home directory could be found
+    pub fn resolve() -> Option<Self> {
+        let project_dirs = ProjectDirs::from("", "", "ripen")?;
+        let config_dir = project_dirs.config_local_dir();
+        Some(Self {
+            lua_config: config_dir.join("functions.lua"),
+            uiua_config: config_dir.join("functions.ua"),
+            settings: config_dir.join("config.toml"),
+            keybinds: config_dir.join("keybinds.toml"),
+            aliases: config_dir.join("aliases.toml"),
+            data_dir: Some(project_dirs.data_local_dir().to_path_buf()),
+        })
+    }
+
+    // checked once at startup so a read-only filesystem produces one warning instead of one per save
+    pub fn ensure_writable(&mut self) -> Result<(), String> {
+        let Some(data_dir) = &self.data_dir else { return Ok(()) };
+        let probe = data_dir.join(".write-test");
+        let result = fs::create_dir_all(data_dir)
+            .and_then(|()| fs::write(&probe, []))
+            .and_then(|()| fs::remove_file(&probe));
+        if let Err(e) = result {
+            let message = format!("{} is not writable ({e}); nothing will be saved this session", data_dir.display());
+            self.data_dir = None;
+            return Err(message);
+        }
+        Ok(())
+    }
+}
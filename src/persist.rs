@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+// numbers are stored as literals so exact values survive the round trip
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub stack: Vec<String>,
+    pub history: Vec<String>,
+    // the workspace the stack and history belong to, and every other one. older sessions have neither
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub workspaces: Vec<SavedWorkspace>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedWorkspace {
+    pub name: String,
+    pub stack: Vec<String>,
+    pub history: Vec<String>,
+}
+
+// Ok(None) if there's no saved session yet
+pub fn load(path: impl AsRef<Path>) -> Result<Option<Session>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map(Some).map_err(|e| format!("Saved session is corrupt: {e}")),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Unable to read saved session: {e}")),
+    }
+}
+
+pub fn save(path: impl AsRef<Path>, session: &Session) -> Result<(), String> {
+    // unwrap safety: a struct of strings always serializes
+    let text = serde_json::to_string_pretty(session).unwrap();
+    fs::write(path, text).map_err(|e| format!("Unable to save session: {e}"))
+}
+
+// how many times each operation has run, kept apart from the session so --no-restore doesn't lose it.
+// a missing file means nothing has been counted yet
+pub fn load_usage(path: impl AsRef<Path>) -> Result<HashMap<String, u64>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("Saved usage statistics are corrupt: {e}")),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(format!("Unable to read usage statistics: {e}")),
+    }
+}
+
+pub fn save_usage(path: impl AsRef<Path>, usage: &HashMap<String, u64>) -> Result<(), String> {
+    // unwrap safety: a map of strings to numbers always serializes
+    let text = serde_json::to_string_pretty(usage).unwrap();
+    fs::write(path, text).map_err(|e| format!("Unable to save usage statistics: {e}"))
+}
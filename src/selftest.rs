@@ -0,0 +1,220 @@
+use crate::{angle::AngleMode, value::Value, Calculator, Operation, BASE_LUA};
+
+use std::{f64::consts::{E, PI, SQRT_2, TAU}, sync::mpsc};
+
+// (operation, stack before, expected stack after). None means the operation should fail
+type Case = (&'static str, &'static [f64], Option<&'static [f64]>);
+
+const CASES: &[Case] = &[
+    ("+", &[2.0, 3.0], Some(&[5.0])),
+    ("-", &[2.0, 3.0], Some(&[-1.0])),
+    ("*", &[2.0, 3.0], Some(&[6.0])),
+    ("/", &[3.0, 2.0], Some(&[1.5])),
+    ("/", &[1.0, 0.0], None),
+    ("+", &[f64::MAX, f64::MAX], None),
+    ("-", &[f64::MAX, -f64::MAX], None),
+    ("*", &[f64::MAX, 2.0], None),
+    ("pctof", &[f64::MAX, 200.0], None),
+    ("^", &[2.0, 10.0], Some(&[1024.0])),
+    ("fact", &[0.0], Some(&[1.0])),
+    ("fact", &[5.0], Some(&[120.0])),
+    ("fact", &[25.0], Some(&[1.5511210043330986e25])),
+    ("fact", &[-1.0], None),
+    ("fact", &[2.5], None),
+    ("fact", &[171.0], None),
+    ("gcd", &[12.0, 18.0], Some(&[6.0])),
+    ("gcd", &[-4.0, 0.0], Some(&[4.0])),
+    ("gcd", &[1.5, 3.0], None),
+    ("lcm", &[4.0, 6.0], Some(&[12.0])),
+    ("lcm", &[0.0, 6.0], Some(&[0.0])),
+    ("lcm", &[-4.0, 6.0], Some(&[12.0])),
+    ("mod", &[7.0, 3.0], Some(&[1.0])),
+    ("mod", &[-7.0, 3.0], Some(&[2.0])),
+    ("mod", &[5.5, 2.0], Some(&[1.5])),
+    ("mod", &[1.0, 0.0], None),
+    ("%", &[7.0, 3.0], Some(&[1.0])),
+    ("%", &[-7.0, 3.0], Some(&[-1.0])),
+    ("%", &[7.0, -3.0], Some(&[1.0])),
+    ("%", &[-7.0, -3.0], Some(&[-1.0])),
+    ("%", &[5.5, 2.0], Some(&[1.5])),
+    ("%", &[1.0, 0.0], None),
+    ("round", &[2.5], Some(&[3.0])),
+    ("round", &[-2.5], Some(&[-3.0])),
+    ("round", &[2.4], Some(&[2.0])),
+    ("floor", &[-2.5], Some(&[-3.0])),
+    ("ceil", &[-2.5], Some(&[-2.0])),
+    ("ceil", &[2.1], Some(&[3.0])),
+    ("trunc", &[-2.7], Some(&[-2.0])),
+    ("trunc", &[2.7], Some(&[2.0])),
+    ("roundto", &[1.23456, 2.0], Some(&[1.23])),
+    ("roundto", &[2.675, 0.0], Some(&[3.0])),
+    ("roundto", &[1234.5678, 1.0], Some(&[1234.6])),
+    ("roundto", &[1e-20, 400.0], Some(&[1e-20])),
+    ("roundto", &[PI, -1.0], None),
+    ("roundto", &[PI, 1.5], None),
+    ("and", &[12.0, 10.0], Some(&[8.0])),
+    ("and", &[1.5, 1.0], None),
+    ("or", &[12.0, 10.0], Some(&[14.0])),
+    ("xor", &[12.0, 10.0], Some(&[6.0])),
+    ("not", &[0.0], Some(&[-1.0])),
+    ("shl", &[1.0, 4.0], Some(&[16.0])),
+    ("shl", &[1.0, 64.0], None),
+    ("shr", &[-8.0, 1.0], Some(&[-4.0])),
+    ("shr", &[8.0, -1.0], None),
+    ("+", &[1.0], None),
+    ("sin", &[PI / 2.0], Some(&[1.0])),
+    ("cos", &[0.0], Some(&[1.0])),
+    ("tan", &[PI / 4.0], Some(&[1.0])),
+    ("tan", &[PI / 2.0], None),
+    ("sin", &[f64::INFINITY], None),
+    ("asin", &[1.0], Some(&[PI / 2.0])),
+    ("asin", &[2.0], None),
+    ("acos", &[1.0], Some(&[0.0])),
+    ("atan", &[1.0], Some(&[PI / 4.0])),
+    ("ln", &[1.0], Some(&[0.0])),
+    ("ln", &[-1.0], None),
+    ("drg", &[1.0], Some(&[1.0])),
+    ("swap", &[1.0, 2.0], Some(&[2.0, 1.0])),
+    ("dup", &[1.0], Some(&[1.0, 1.0])),
+    ("drop", &[1.0, 2.0], Some(&[1.0])),
+    ("over", &[1.0, 2.0], Some(&[1.0, 2.0, 1.0])),
+    ("rot", &[1.0, 2.0, 3.0], Some(&[2.0, 3.0, 1.0])),
+    ("clear", &[1.0, 2.0], Some(&[])),
+    ("reverse", &[], Some(&[])),
+    ("reverse", &[1.0], Some(&[1.0])),
+    ("reverse", &[1.0, 2.0, 2.0, 3.0], Some(&[3.0, 2.0, 2.0, 1.0])),
+    ("uniq", &[1.0, 1.0, 2.0, 1.0, 1.0], Some(&[1.0, 2.0, 1.0])),
+    ("uniq", &[0.1 + 0.2, 0.3], Some(&[0.1 + 0.2, 0.3])),
+    ("dedup", &[1.0, 2.0, 1.0, 3.0, 2.0], Some(&[1.0, 2.0, 3.0])),
+    ("dedup", &[], Some(&[])),
+    ("sort", &[], Some(&[])),
+    ("sort", &[1.0], Some(&[1.0])),
+    ("sort", &[3.0, 1.0, 3.0, 2.0], Some(&[1.0, 2.0, 3.0, 3.0])),
+    ("rsort", &[], Some(&[])),
+    ("rsort", &[1.0], Some(&[1.0])),
+    ("rsort", &[3.0, 1.0, 3.0, 2.0], Some(&[3.0, 3.0, 2.0, 1.0])),
+    ("m2ft", &[0.3048], Some(&[1.0])),
+    ("ft2m", &[10.0], Some(&[3.048])),
+    ("cm2in", &[2.54], Some(&[1.0])),
+    ("in2cm", &[1.0], Some(&[2.54])),
+    ("km2mi", &[1.609344], Some(&[1.0])),
+    ("mi2km", &[1.0], Some(&[1.609344])),
+    ("kg2lb", &[0.45359237], Some(&[1.0])),
+    ("lb2kg", &[1.0], Some(&[0.45359237])),
+    ("g2oz", &[28.349523125], Some(&[1.0])),
+    ("oz2g", &[1.0], Some(&[28.349523125])),
+    ("c2f", &[100.0], Some(&[212.0])),
+    ("c2f", &[-40.0], Some(&[-40.0])),
+    ("f2c", &[32.0], Some(&[0.0])),
+    ("c2k", &[0.0], Some(&[273.15])),
+    ("k2c", &[0.0], Some(&[-273.15])),
+    ("nounit", &[2.0], Some(&[2.0])),
+    ("sqrt", &[16.0], Some(&[4.0])),
+    ("abs", &[-2.5], Some(&[2.5])),
+    ("abs", &[3.0], Some(&[3.0])),
+    ("cbrt", &[27.0], Some(&[3.0])),
+    ("pct", &[50.0], Some(&[0.5])),
+    ("pctof", &[200.0, 15.0], Some(&[30.0])),
+    ("pctchg", &[80.0, 100.0], Some(&[25.0])),
+    ("pctchg", &[0.0, 100.0], None),
+    ("pi", &[], Some(&[PI])),
+    ("e", &[], Some(&[E])),
+    ("tau", &[], Some(&[TAU])),
+    ("phi", &[], Some(&[1.618_033_988_749_895])),
+    ("sqrt2", &[], Some(&[SQRT_2])),
+    ("c", &[], Some(&[299_792_458.0])),
+    ("na", &[], Some(&[6.022_140_76e23])),
+    ("h", &[], Some(&[6.626_070_15e-34])),
+    ("g", &[], Some(&[9.806_65])),
+    ("digits", &[305.0], Some(&[3.0, 0.0, 5.0])),
+    ("digits", &[-1.0], None),
+    ("undigits", &[3.0, 0.0, 5.0, 3.0], Some(&[305.0])),
+    ("sumn", &[9.0, 1.0, 2.0, 3.0, 3.0], Some(&[9.0, 6.0])),
+    ("sumn", &[1.0, -1.0], None),
+    ("total", &[1.0, 2.0, 3.5], Some(&[6.5])),
+    ("total", &[], None),
+    ("product", &[2.0, 3.0, 4.0], Some(&[24.0])),
+    ("stackmax", &[3.0, 7.0, -2.0], Some(&[7.0])),
+    ("stackmin", &[3.0, 7.0, -2.0], Some(&[-2.0])),
+    ("prodn", &[2.0, 3.0, 2.0], Some(&[6.0])),
+    ("prodn", &[2.0, 3.0, 3.0], None),
+    ("dupn", &[1.0, 2.0, 0.0], Some(&[1.0, 2.0])),
+    ("dupn", &[1.0, 2.0, 1.0], Some(&[1.0, 2.0, 2.0])),
+    ("dupn", &[1.0, 2.0, 2.0], Some(&[1.0, 2.0, 1.0, 2.0])),
+    ("dupn", &[1.0, 2.0, 3.0], None),
+    ("pick", &[1.0, 2.0, 3.0, 0.0], Some(&[1.0, 2.0, 3.0, 3.0])),
+    ("pick", &[1.0, 2.0, 3.0, 2.0], Some(&[1.0, 2.0, 3.0, 1.0])),
+    ("pick", &[1.0, 2.0, 3.0, 3.0], None),
+    ("pick", &[1.0, 2.0, 1e20], None),
+    ("depth", &[], Some(&[0.0])),
+    ("depth", &[4.0, 5.0], Some(&[4.0, 5.0, 2.0])),
+    ("roll", &[1.0, 2.0, 3.0, 2.0], Some(&[2.0, 3.0, 1.0])),
+    ("roll", &[1.0, 2.0, 3.0, 0.0], Some(&[1.0, 2.0, 3.0])),
+    ("roll", &[1.0, 2.0, 3.0, 3.0], None),
+    ("roll", &[1.0, 2.0, 1e20], None),
+    ("sumrange", &[1.0, 5.0, 1.0], Some(&[10.0])),
+    ("prodrange", &[1.0, 5.0, 1.0], Some(&[24.0])),
+    ("sumrange", &[1.0, 5.0, 0.0], None),
+    ("neg", &[2.0], Some(&[-2.0])),
+    ("d2r", &[180.0], Some(&[PI])),
+    ("r2d", &[PI], Some(&[180.0])),
+    ("root", &[8.0, 3.0], Some(&[2.0])),
+    ("min", &[2.0, 3.0], Some(&[2.0])),
+    ("max", &[2.0, 3.0], Some(&[3.0])),
+];
+
+fn close(a: &[f64], b: &[f64]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() <= 1e-9 * x.abs().max(y.abs()).max(1.0))
+}
+
+// checks every case on a fresh calculator with only the built-ins, so no setting or config can change the results.
+// prints one line per failure and returns whether all of them passed.
+// machine readable output is tab separated: status, operation, input, expected, actual
+pub fn run(machine_readable: bool) -> bool {
+    let mut calc = Calculator::new();
+    if let Err(e) = calc.load_lua(BASE_LUA) {
+        println!("FAIL base Lua config: {e}");
+        return false;
+    }
+    let (tx, _rx) = mpsc::channel();
+    let mut failed = 0;
+    for &(op, input, expected) in CASES {
+        calc.stack = input.iter().copied().map(Value::from).collect();
+        // trig cases are written in radians
+        calc.angle_mode = AngleMode::Radians;
+        let success = calc.operate(op.into(), tx.clone());
+        let stack: Vec<f64> = calc.stack.iter().map(|n| n.to_f64()).collect();
+        let passed = match expected {
+            Some(expected) => success && close(&stack, expected),
+            None => !success && stack == input,
+        };
+        if !passed {
+            failed += 1;
+        }
+        let expected = expected.map_or("failure".into(), |e| format!("{e:?}"));
+        let actual = if success { format!("{stack:?}") } else { "failure".into() };
+        if machine_readable {
+            println!("{}\t{op}\t{input:?}\t{expected}\t{actual}", if passed { "pass" } else { "fail" });
+        } else if !passed {
+            println!("FAIL {op} on {input:?}: expected {expected}, got {actual}");
+        }
+    }
+
+    let mut untested: Vec<&String> = calc.operations.iter()
+        .filter(|(name, op)| matches!(op, Operation::Rust(..)) && !CASES.iter().any(|(case, ..)| case == name))
+        .map(|(name, _)| name)
+        .collect();
+    untested.sort();
+    for name in &untested {
+        if machine_readable {
+            println!("untested\t{name}\t\t\t");
+        } else {
+            println!("UNTESTED {name}");
+        }
+    }
+
+    if !machine_readable {
+        println!("{} passed, {failed} failed, {} untested", CASES.len() - failed, untested.len());
+    }
+    failed == 0
+}
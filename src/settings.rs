@@ -0,0 +1,180 @@
+use serde::Deserialize;
+
+use crate::{layout::LayoutConfig, theme::ThemeConfig};
+
+use std::{fs, io, path::Path, time::Duration};
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // reload functions.lua/functions.ua automatically when they change on disk
+    pub watch_config: bool,
+    // keep operations made with `def` across sessions
+    pub save_definitions: bool,
+    // also read as `stack_order`, with `newest_bottom` and `newest_top`
+    #[serde(alias = "stack_order")]
+    pub growth_direction: GrowthDirection,
+    // backspace with nothing typed drops the top of the stack
+    pub backspace_pops_stack: bool,
+    // the reset key has to be pressed twice to clear the stack
+    pub confirm_reset: bool,
+    // what Enter does with nothing typed
+    pub empty_enter: EmptyEnterAction,
+    pub stack_labels: StackLabels,
+    pub digit_grouping: DigitGrouping,
+    // negative values in the stack pane are colored, zero is dimmed, and infinities and NaN stand out
+    pub color_by_sign: bool,
+    // seconds before an error disappears; 0 keeps errors until they're dismissed
+    pub error_timeout: f64,
+    // warn when a Uiua function leaves a different number of values than its signature says, or takes values and leaves none
+    pub warn_output_count: bool,
+    // seconds a Lua or Uiua function may run before it's stopped; 0 lets them run forever
+    pub operation_timeout: f64,
+    // significant digits kept from Lua and Uiua results, which changes the stored value; 0 keeps them all
+    pub significant_digits: u32,
+    // the most values the stack can hold, counting a vector as one; 0 means no limit
+    pub max_stack: usize,
+    pub stack_overflow: OverflowPolicy,
+    // how far apart two numbers can be and still count as equal to uniq and dedup; 0 is exact
+    pub equality_epsilon: f64,
+    // the biggest denominator tofrac will use
+    pub max_denominator: i64,
+    // what total and product do with an empty stack
+    pub empty_total: EmptyTotal,
+    // clicking a stack value pushes a copy, and the wheel scrolls
+    pub mouse: bool,
+    // milliseconds between idle wakeups
+    pub tick_rate_ms: u64,
+    pub theme: ThemeConfig,
+    pub layout: LayoutConfig,
+}
+
+// which way the stack pane grows as values are pushed
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GrowthDirection {
+    // newest value at the bottom of the pane
+    #[default]
+    #[serde(alias = "newest_bottom")]
+    Down,
+    // newest value at the top of the pane
+    #[serde(alias = "newest_top")]
+    Up,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            watch_config: false,
+            save_definitions: true,
+            growth_direction: GrowthDirection::default(),
+            backspace_pops_stack: false,
+            confirm_reset: true,
+            empty_enter: EmptyEnterAction::default(),
+            stack_labels: StackLabels::default(),
+            digit_grouping: DigitGrouping::default(),
+            color_by_sign: false,
+            error_timeout: 4.0,
+            warn_output_count: true,
+            operation_timeout: 5.0,
+            significant_digits: 0,
+            max_stack: 1_000_000,
+            stack_overflow: OverflowPolicy::default(),
+            equality_epsilon: 0.0,
+            max_denominator: 10_000,
+            empty_total: EmptyTotal::default(),
+            mouse: true,
+            tick_rate_ms: 200,
+            theme: ThemeConfig::default(),
+            layout: LayoutConfig::default(),
+        }
+    }
+}
+
+impl Settings {
+    // a missing file is not an error, it just means everything is default
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("Unable to parse config.toml: {e}")),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Unable to read config.toml: {e}")),
+        }
+    }
+
+    // None if errors should stay until dismissed. a negative timeout counts as the default
+    pub fn error_timeout(&self) -> Option<Duration> {
+        let timeout = Duration::try_from_secs_f64(self.error_timeout).unwrap_or(Duration::from_secs(4));
+        (!timeout.is_zero()).then_some(timeout)
+    }
+
+    // None if functions can run forever. a negative timeout counts as the default
+    pub fn operation_timeout(&self) -> Option<Duration> {
+        let timeout = Duration::try_from_secs_f64(self.operation_timeout).unwrap_or(Duration::from_secs(5));
+        (!timeout.is_zero()).then_some(timeout)
+    }
+}
+
+// what number each row of the stack pane is labelled with
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StackLabels {
+    // 0 is the newest value
+    #[default]
+    Depth,
+    // 1 is the oldest value
+    Position,
+    None,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyTotal {
+    #[default]
+    Error,
+    // 0 for total and 1 for product, which is what adding or multiplying nothing gives
+    Identity,
+}
+
+// how the stack pane separates groups of digits in big numbers. copies are never grouped
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DigitGrouping {
+    #[default]
+    None,
+    Space,
+    Comma,
+    Underscore,
+}
+
+impl DigitGrouping {
+    pub fn separator(self) -> Option<char> {
+        match self {
+            Self::None => None,
+            Self::Space => Some(' '),
+            Self::Comma => Some(','),
+            Self::Underscore => Some('_'),
+        }
+    }
+}
+
+// what happens when an entry leaves more than max_stack values
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    // undo the whole entry and show an error
+    #[default]
+    Reject,
+    // keep the newest values and drop the oldest
+    DropOldest,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyEnterAction {
+    // run the last operation again
+    #[default]
+    RepeatPrevious,
+    Noop,
+    // push a copy of the top value
+    DupTop,
+}
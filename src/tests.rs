@@ -0,0 +1,978 @@
+use crate::{
+    error::OpError, format::{Base, DisplayFormat}, settings::{EmptyEnterAction, EmptyTotal, GrowthDirection, OverflowPolicy, Settings}, unit::Unit, value::Value,
+    draw, finish_background, selftest, strip_comment, submit, uiua_output_warning, Calculator, CursorMove, Event, Mode, Operation, BASE_LUA, MIN_HEIGHT, MIN_WIDTH,
+};
+
+use ratatui::{backend::TestBackend, layout::Rect, style::{Modifier, Style}, Terminal};
+
+use std::{f64::consts::PI, fs, sync::{atomic::Ordering, mpsc}, time::{Duration, Instant}};
+
+// a calculator with the built-in operations and nothing from the user's configs
+fn calculator() -> Calculator {
+    let mut calc = Calculator::new();
+    calc.load_lua(BASE_LUA).expect("the base Lua config should load");
+    calc
+}
+
+// runs `op` on a stack of `input`, giving the stack it leaves, or None if it fails and leaves the stack alone
+fn apply(calc: &mut Calculator, op: &str, input: &[f64]) -> Option<Vec<f64>> {
+    let (tx, _rx) = mpsc::channel();
+    calc.stack = input.iter().copied().map(Value::from).collect();
+    let succeeded = calc.operate(op.into(), tx);
+    let stack: Vec<f64> = calc.stack.iter().map(Value::to_f64).collect();
+    if !succeeded {
+        assert_eq!(stack, input, "{op} failed but changed the stack");
+    }
+    succeeded.then_some(stack)
+}
+
+// a failing Uiua function shouldn't leave anything behind that breaks the next call
+#[test]
+fn uiua_recovers() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    let code = "TestFail ← [⊢[]]\nTestAdd ← +";
+    calc.uiua.run_str(code).expect("the Uiua code should compile");
+    calc.bind_uiua(code);
+    assert!(!calc.operate("testfail".into(), tx.clone()), "taking the first of an empty array should fail");
+    calc.stack = vec![Value::from(1.0), Value::from(2.0)];
+    assert!(calc.operate("testadd".into(), tx), "a Uiua function failed to run after another one failed");
+    assert_eq!(calc.stack, [Value::from(3.0)]);
+}
+
+// every error should expire exactly once, and none before its time
+#[test]
+fn errors_expire() {
+    let mut calc = calculator();
+    let timeout = Duration::from_secs(4);
+    calc.error_timeout = Some(timeout);
+    let start = Instant::now();
+    for i in 0..5 {
+        calc.push_error(format!("test error {i}"));
+    }
+    assert_eq!(calc.expire_errors(start), 0, "an error expired before its time");
+    assert_eq!(calc.expire_errors(Instant::now() + timeout), 5);
+    assert_eq!(calc.expire_errors(Instant::now() + timeout * 2), 0, "an error expired twice");
+    assert!(calc.errors.is_empty());
+}
+
+// dupn copies the top n values as a block, in order, and the count itself isn't copied
+#[test]
+fn dupn_copies_block() {
+    let mut calc = calculator();
+    assert_eq!(apply(&mut calc, "dupn", &[1.0, 2.0, 0.0]), Some(vec![1.0, 2.0]));
+    assert_eq!(apply(&mut calc, "dupn", &[1.0, 2.0, 2.0]), Some(vec![1.0, 2.0, 1.0, 2.0]));
+    assert_eq!(apply(&mut calc, "dupn", &[1.0, 2.0, 3.0]), None);
+    assert_eq!(apply(&mut calc, "dupn", &[1.0, 1.5]), None);
+    let third = Value::parse("1/3").expect("1/3 should parse");
+    calc.stack = vec![third.clone(), Value::from(1.0)];
+    let (tx, _rx) = mpsc::channel();
+    assert!(calc.operate("dupn".into(), tx));
+    assert_eq!(calc.stack, [third.clone(), third], "dupn turned a fraction into a float");
+}
+
+// a second chunk adds to the functions from the first instead of replacing them
+#[test]
+fn lua_loads_merge() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    let first = calc.load_lua("register(\"TestOne\", 1, function(x) return x + 1 end)").expect("the first chunk should load");
+    let second = calc.load_lua("register(\"TestTwo\", 1, function(x) return x * 2 end)").expect("the second chunk should load");
+    assert!(first.contains("testone") && second.contains("testtwo") && !second.contains("testone"));
+    calc.stack = vec![Value::from(3.0)];
+    assert!(calc.operate("testone".into(), tx.clone()), "a Lua function stopped working after another chunk was loaded");
+    assert!(calc.operate("testtwo".into(), tx));
+    assert_eq!(calc.stack, [Value::from(8.0)]);
+}
+
+// sorting moves the values themselves, so fractions stay exact and lengths compare across units
+#[test]
+fn sort_keeps_values() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    let half = Value::parse("1/2").expect("1/2 should parse");
+    calc.stack = vec![Value::from(1.0), half.clone()];
+    assert!(calc.operate("sort".into(), tx.clone()));
+    assert_eq!(calc.stack, [half, Value::from(1.0)], "sort turned a fraction into a float");
+    calc.stack = vec![Value::Quantity(1.0, Unit::Meter), Value::Quantity(50.0, Unit::Centimeter)];
+    assert!(calc.operate("sort".into(), tx.clone()));
+    assert_eq!(calc.stack, [Value::Quantity(50.0, Unit::Centimeter), Value::Quantity(1.0, Unit::Meter)]);
+    calc.stack = vec![Value::Quantity(1.0, Unit::Meter), Value::from(2.0)];
+    assert!(!calc.operate("sort".into(), tx), "sort mixed a number with a unit and one without");
+    assert_eq!(calc.stack.len(), 2);
+}
+
+// an entry that overflows the stack is either undone or pushes out the oldest values
+#[test]
+fn stack_limited() {
+    let mut calc = calculator();
+    calc.settings.max_stack = 3;
+    calc.stack = vec![Value::from(1.0)];
+    calc.settings.stack_overflow = OverflowPolicy::Reject;
+    assert!(calc.eval_line("2 3 4").is_err(), "going past max_stack didn't reject the entry");
+    assert_eq!(calc.stack, [Value::from(1.0)]);
+    assert_eq!(calc.text_box, "2 3 4");
+    calc.text_box.clear();
+    calc.settings.stack_overflow = OverflowPolicy::DropOldest;
+    assert!(calc.eval_line("2 3 4").is_ok());
+    assert_eq!(calc.stack, [Value::from(2.0), Value::from(3.0), Value::from(4.0)], "the oldest value wasn't dropped");
+    // a click that would overflow is rejected without touching what's being typed
+    calc.settings.stack_overflow = OverflowPolicy::Reject;
+    calc.text_box = "12".into();
+    calc.stack_click(0);
+    assert_eq!(calc.stack.len(), 3);
+    assert_eq!(calc.text_box, "12");
+}
+
+// with an epsilon, near-equal floats count as duplicates, but ones further apart don't
+#[test]
+fn epsilon_dedups() {
+    let mut calc = calculator();
+    calc.epsilon.set(1e-9);
+    let near = [0.1 + 0.2, 0.3, 0.3 + 1e-6];
+    assert_eq!(apply(&mut calc, "uniq", &near), Some(vec![near[0], near[2]]), "uniq kept a near-equal value");
+    assert_eq!(apply(&mut calc, "dedup", &[near[0], 1.0, near[1]]), Some(vec![near[0], 1.0]), "dedup kept a near-equal value");
+}
+
+// depth counts the values under it, and works on an empty stack
+#[test]
+fn depth_counts_stack() {
+    let mut calc = calculator();
+    assert_eq!(apply(&mut calc, "depth", &[]), Some(vec![0.0]));
+    assert_eq!(apply(&mut calc, "depth", &[4.0, 5.0]), Some(vec![4.0, 5.0, 2.0]));
+}
+
+// drawing never panics, however small the terminal gets. below the minimum only a message is drawn
+#[test]
+fn small_terminals() {
+    let mut calc = calculator();
+    calc.stack = vec![Value::from(1.0), Value::from(2.0)];
+    calc.push_error("test error");
+    for (width, height) in [(1, 1), (2, 5), (5, 2), (11, 40), (MIN_WIDTH, MIN_HEIGHT), (80, 24)] {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).expect("a test terminal should open");
+        terminal.draw(|f| draw(f, &calc)).expect("drawing into a test terminal should work");
+    }
+    let mut terminal = Terminal::new(TestBackend::new(2, 5)).expect("a test terminal should open");
+    terminal.draw(|f| draw(f, &calc)).expect("drawing into a test terminal should work");
+    assert!(terminal.backend().buffer().content().first().is_some_and(|cell| cell.symbol == "T"), "a tiny terminal didn't say it was too small");
+}
+
+// % truncates toward zero, so the remainder takes the sign of the dividend, unlike mod
+#[test]
+fn percent_truncates() {
+    let mut calc = calculator();
+    assert_eq!(apply(&mut calc, "%", &[7.0, 3.0]), Some(vec![1.0]));
+    assert_eq!(apply(&mut calc, "%", &[-7.0, 3.0]), Some(vec![-1.0]));
+    assert_eq!(apply(&mut calc, "%", &[7.0, -3.0]), Some(vec![1.0]));
+    assert_eq!(apply(&mut calc, "mod", &[-7.0, 3.0]), Some(vec![2.0]));
+    assert_eq!(apply(&mut calc, "%", &[5.5, 2.0]), Some(vec![1.5]));
+    assert_eq!(apply(&mut calc, "%", &[1.0, 0.0]), None);
+}
+
+// a config's top level runs once when it loads, not again before every call
+#[test]
+fn uiua_top_level_once() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    let code = "TestDouble ← ×2\n7";
+    calc.uiua.run_str(code).expect("the Uiua code should compile");
+    calc.bind_uiua(code);
+    for _ in 0..2 {
+        calc.stack = vec![Value::from(3.0)];
+        assert!(calc.operate("testdouble".into(), tx.clone()));
+        assert_eq!(calc.stack, [Value::from(6.0)], "the config's top level ran again, and its 7 came back as output");
+    }
+}
+
+// a Uiua function that leaves what its signature says doesn't warn, and one that doesn't would
+#[test]
+fn output_counts_match() {
+    let mut calc = calculator();
+    let (tx, rx) = mpsc::channel();
+    calc.uiua.run_str("TestAdd ← +").expect("the Uiua code should compile");
+    calc.bind_uiua("TestAdd ← +");
+    calc.stack = vec![Value::from(1.0), Value::from(2.0)];
+    assert!(calc.operate("testadd".into(), tx));
+    assert!(!rx.try_iter().any(|event| matches!(event, Event::PushError(OpError::Warning(_)))), "a matching output count warned");
+    // a real mismatch can't be compiled, so the decision is checked on its own
+    assert!(matches!(uiua_output_warning("f", 2, 1, 2), Some(OpError::Warning(_))));
+    assert!(matches!(uiua_output_warning("f", 1, 1, 0), Some(OpError::Warning(_))));
+    assert!(uiua_output_warning("f", 2, 1, 1).is_none());
+    assert!(uiua_output_warning("f", 0, 0, 0).is_none());
+}
+
+// a Uiua function that takes values and leaves none warns, unless warn_output_count is off
+#[test]
+fn empty_output_warns() {
+    let mut calc = calculator();
+    let (tx, rx) = mpsc::channel();
+    calc.uiua.run_str("TestDiscard ← ◌").expect("the Uiua code should compile");
+    calc.bind_uiua("TestDiscard ← ◌");
+    let warned = |rx: &mpsc::Receiver<Event>| rx.try_iter().any(|event| matches!(event, Event::PushError(OpError::Warning(_))));
+    calc.stack = vec![Value::from(5.0)];
+    assert!(calc.operate("testdiscard".into(), tx.clone()) && calc.stack.is_empty());
+    assert!(warned(&rx), "a function that took a value and left none didn't warn");
+    calc.settings.warn_output_count = false;
+    calc.stack = vec![Value::from(5.0)];
+    assert!(calc.operate("testdiscard".into(), tx) && calc.stack.is_empty());
+    assert!(!warned(&rx), "warn_output_count was off but it still warned");
+}
+
+// numeric constants bound in Uiua become operations that push them, and anything else is skipped with a note in the log
+#[test]
+fn uiua_constants() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    let code = "TestConst ← 42\nTestList ← [1 2 3]\nTestText ← \"hi\"";
+    calc.uiua.run_str(code).expect("the Uiua code should compile");
+    calc.bind_uiua(code);
+    assert!(calc.operate("testconst".into(), tx.clone()) && calc.operate("testlist".into(), tx));
+    assert_eq!(calc.stack.len(), 2);
+    assert_eq!(calc.stack[0].to_f64(), 42.0);
+    assert_eq!(calc.stack[1], Value::Vector(vec![1.0, 2.0, 3.0]));
+    assert!(!calc.operations.contains_key("testtext"), "a string became an operation");
+    assert!(calc.log.iter().any(|(_, line)| line.contains("TestText")), "a skipped binding wasn't logged");
+}
+
+// `def:name` and the inspect key both show the line that bound a Uiua function
+#[test]
+fn definitions_shown() {
+    let mut calc = calculator();
+    calc.uiua.run_str("TestAdd ← +").expect("the Uiua code should compile");
+    calc.bind_uiua("TestAdd ← +");
+    let shows_binding = |calc: &Calculator| calc.overlay.as_ref().is_some_and(|o| o.lines.iter().any(|line| line == "TestAdd ← +"));
+    assert!(calc.eval_line("def:testadd").is_ok() && shows_binding(&calc), "def:name didn't show the binding");
+    calc.overlay = None;
+    calc.text_box = "2 TestAdd".into();
+    assert!(calc.inspect().is_ok() && shows_binding(&calc), "the inspect key didn't show the binding");
+    calc.text_box.clear();
+    assert!(calc.eval_line("def:testnothing").is_err());
+}
+
+// digits and undigits split and join in the output base, and name what was wrong when they fail
+#[test]
+fn digits_in_base() {
+    let mut calc = calculator();
+    calc.output_base = Base::Hexadecimal;
+    calc.stack = vec![Value::from(31.0)];
+    assert!(calc.eval_line("digits").is_ok());
+    assert_eq!(calc.stack, [Value::integer(1), Value::integer(15)]);
+    calc.stack.push(Value::from(2.0));
+    assert!(calc.eval_line("undigits").is_ok());
+    assert_eq!(calc.stack, [Value::integer(31)]);
+    // the preview, macros, and aliases all see the same base
+    calc.text_box = "digits".into();
+    assert_eq!(calc.preview(), Some(calc.format_value(&Value::integer(15))), "the preview split the digits in decimal");
+    calc.text_box.clear();
+    calc.operations.insert("split".into(), Operation::Macro(vec!["digits".into()]));
+    assert!(calc.eval_line("split").is_ok());
+    assert_eq!(calc.stack, [Value::integer(1), Value::integer(15)], "a macro split the digits in decimal");
+    calc.output_base = Base::Binary;
+    calc.stack = vec![Value::from(1.0), Value::from(2.0), Value::from(2.0)];
+    assert!(calc.eval_line("undigits").is_err(), "2 was taken as a binary digit");
+    assert_eq!(calc.stack.len(), 3);
+}
+
+// `show` lists what a macro calls and what calls it, one level deep
+#[test]
+fn dependencies_shown() {
+    let mut calc = calculator();
+    calc.operations.insert("testsq".into(), Operation::Macro(vec!["dup".into(), "*".into()]));
+    calc.operations.insert("testquad".into(), Operation::Macro(vec!["TestSq".into(), "testsq".into()]));
+    let lines = |calc: &Calculator| calc.overlay.as_ref().map(|o| o.lines.clone()).unwrap_or_default();
+    assert!(calc.eval_line("show testsq").is_ok());
+    assert!(lines(&calc).iter().any(|l| l == "Uses: dup, *"));
+    assert!(lines(&calc).iter().any(|l| l == "Used by: testquad"));
+    assert!(calc.eval_line("show testquad").is_ok());
+    assert!(lines(&calc).iter().any(|l| l == "Uses: testsq"));
+    assert!(!lines(&calc).iter().any(|l| l.starts_with("Used by")), "nothing calls testquad, but it listed something");
+}
+
+// the ripen table reaches the stack under a function's arguments, and nothing outside a call
+const LUA_RIPEN: &str = r#"
+register("testripen", 1, function(x)
+  ripen.push(ripen.pop() * 10)
+  ripen.error("test " .. ripen.angle)
+  return x + ripen.depth()
+end)
+local ok = pcall(ripen.push, 1)
+assert(not ok)
+"#;
+
+#[test]
+fn lua_ripen_table() {
+    let mut calc = calculator();
+    let (tx, rx) = mpsc::channel();
+    calc.load_lua(LUA_RIPEN).expect("the Lua code should load");
+    calc.stack = vec![Value::from(2.0), Value::from(3.0)];
+    assert!(calc.operate("testripen".into(), tx));
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [20.0, 4.0]);
+    assert!(rx.try_iter().any(|event| matches!(event, Event::PushError(OpError::LuaError(m)) if m == "test rad")), "ripen.error didn't show");
+}
+
+// register_stack functions keep the values they return unchanged, even when they move them
+#[test]
+fn lua_stack_keeps_values() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    let code = "register_stack(\"TestSort\", function(s) table.sort(s) return s end)\n\
+        register_stack(\"TestBump\", function(s) s[#s] = s[#s] + 1 return s end)";
+    calc.load_lua(code).expect("the Lua code should load");
+    let third = Value::parse("1/3").expect("1/3 should parse");
+    let quarter = Value::parse("1/4").expect("1/4 should parse");
+    calc.stack = vec![Value::from(0.5), third.clone(), quarter.clone()];
+    assert!(calc.operate("testsort".into(), tx.clone()));
+    assert_eq!(calc.stack, [quarter.clone(), third.clone(), Value::from(0.5)], "sorting turned fractions into floats");
+    assert!(calc.operate("testbump".into(), tx));
+    assert_eq!(calc.stack, [quarter, third, Value::from(1.5)]);
+}
+
+// inf, nan, and eps push numbers until an operation takes the name
+#[test]
+fn special_literals() {
+    let mut calc = calculator();
+    assert_eq!(calc.parse_token("eps"), Some(Value::from(f64::EPSILON)));
+    assert_eq!(calc.parse_token("-inf"), Some(Value::from(f64::NEG_INFINITY)));
+    assert!(calc.parse_token("nan").is_some_and(|n| n.to_f64().is_nan()));
+    assert!(calc.define("inf = 1").is_ok());
+    assert_eq!(calc.parse_token("inf"), None, "inf still parsed as a number after an operation took the name");
+    assert!(calc.parse_token("-inf").is_some());
+}
+
+// aliases run their target, and cycles or missing targets are reported instead of added
+#[test]
+fn aliases_load() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    let path = std::env::temp_dir().join(format!("ripen-test-aliases-{}.toml", std::process::id()));
+    fs::write(&path, "add = \"+\"\nplus = \"add\"\nloopa = \"loopb\"\nloopb = \"loopa\"\nmissing = \"nothing\"\n")
+        .expect("the temp dir should be writable");
+    let errors = calc.load_aliases(&path);
+    let _ = fs::remove_file(&path);
+    assert_eq!(errors.len(), 3);
+    calc.stack = vec![Value::from(2.0), Value::from(3.0)];
+    assert!(calc.operate("plus".into(), tx));
+    assert_eq!(calc.stack, [Value::from(5.0)]);
+    for name in ["loopa", "loopb", "missing"] {
+        assert!(!calc.operations.contains_key(name), "the bad alias {name} was added");
+    }
+}
+
+// a line runs the same without a terminal, and failures come back instead of being queued
+#[test]
+fn lines_evaluate() {
+    let mut calc = calculator();
+    assert!(calc.eval_line("1 2 +").is_ok());
+    assert_eq!(calc.stack, [Value::from(3.0)]);
+    assert!(calc.eval_line("4 testnothing 5").is_err());
+    assert_eq!(calc.stack, [Value::from(3.0), Value::from(4.0)]);
+    assert_eq!(calc.text_box, "testnothing 5", "the rest of a failed line wasn't left to fix");
+    assert!(calc.errors.is_empty(), "a failure was queued as well as returned");
+}
+
+// failures say what kind they are, so they can be matched on rather than compared as text
+#[test]
+fn error_kinds() {
+    let mut calc = calculator();
+    calc.stack = vec![Value::from(1.0)];
+    assert_eq!(calc.eval_line("testnothing"), Err(OpError::Unknown("testnothing".into())));
+    calc.text_box.clear();
+    assert_eq!(calc.eval_line("+"), Err(OpError::Arity {op: "+".into(), needed: 2, found: 1}));
+    calc.text_box.clear();
+    assert!(matches!(calc.eval_line("-1 fact"), Err(OpError::Domain(_))));
+}
+
+// replaying an exported script line by line ends with the same stack, even after a change that wasn't an entry
+#[test]
+fn scripts_replay() {
+    let mut calc = calculator();
+    for line in ["1 2", "+", "0.1 0.2 +", "3/4"] {
+        assert!(calc.eval_line(line).is_ok());
+    }
+    // like clicking a value, which isn't an entry
+    calc.stack.insert(0, Value::from(9.0));
+    assert!(calc.eval_line("*").is_ok());
+    let expected = calc.stack.clone();
+    let path = std::env::temp_dir().join(format!("ripen-test-script-{}.ripen", std::process::id()));
+    assert!(calc.eval_line(&format!("exportscript {}", path.display())).is_ok());
+    let script = fs::read_to_string(&path).expect("the script should have been written");
+    let _ = fs::remove_file(&path);
+    assert!(!script.contains("exportscript"));
+    // batch mode starts with an empty stack
+    let mut replay = calculator();
+    for line in script.lines() {
+        assert!(replay.eval_line(line).is_ok(), "`{line}` failed on replay");
+    }
+    assert_eq!(replay.stack, expected);
+    // an empty entry that fails to repeat the last operation isn't written, or replaying it would fail too
+    calc.stack = vec![Value::from(1.0)];
+    calc.script.clear();
+    calc.script_stack = calc.stack.clone();
+    calc.previous = "+".into();
+    let _ = calc.eval_line("");
+    assert!(calc.script.is_empty(), "a failed repeat was written to the script");
+}
+
+// with color_by_sign, negative values get the negative color, zero is dimmed, and anything not finite gets
+// the warning color. without it, nothing is colored
+#[test]
+fn signs_colored() {
+    let mut calc = calculator();
+    assert_eq!(calc.value_style(&Value::from(-1.0)), Style::default());
+    calc.settings.color_by_sign = true;
+    assert_eq!(calc.value_style(&Value::from(-1.0)), calc.theme.negative);
+    assert_eq!(calc.value_style(&Value::from(2.0)), Style::default());
+    assert!(calc.value_style(&Value::from(0.0)).add_modifier.contains(Modifier::DIM));
+    for value in [Value::from(f64::NAN), Value::from(f64::NEG_INFINITY), Value::Vector(vec![1.0, f64::INFINITY])] {
+        assert_eq!(calc.value_style(&value), calc.theme.warning);
+    }
+}
+
+// stack_order is read as growth_direction, and the newest value is drawn, labelled 0, at the end it names
+#[test]
+fn stack_orders() {
+    let parsed = |text: &str| toml::from_str::<Settings>(text).map(|s| s.growth_direction).ok();
+    assert!(parsed("stack_order = \"newest_top\"") == Some(GrowthDirection::Up));
+    assert!(parsed("stack_order = \"newest_bottom\"") == Some(GrowthDirection::Down));
+    let mut calc = calculator();
+    calc.stack = vec![Value::from(1.0), Value::from(2.0), Value::from(3.0)];
+    let row = |calc: &Calculator, y: u16| -> String {
+        let mut terminal = Terminal::new(TestBackend::new(20, 12)).expect("a test terminal should open");
+        terminal.draw(|f| draw(f, calc)).expect("drawing into a test terminal should work");
+        let buffer = terminal.backend().buffer();
+        (0..20).map(|x| buffer.get(x, y).symbol.clone()).collect()
+    };
+    calc.settings.growth_direction = GrowthDirection::Up;
+    assert!(row(&calc, 1).contains("0: 3"));
+    calc.settings.growth_direction = GrowthDirection::Down;
+    assert!(row(&calc, 3).contains("0: 3"));
+}
+
+// only operations that work are counted, a repeat counts each time, and `stats` lists the most used first
+#[test]
+fn usage_counted() {
+    let mut calc = calculator();
+    assert!(calc.eval_line("1 2 + dup").is_ok() && calc.eval_line("3*dup").is_ok());
+    assert!(calc.eval_line("+ + + + + +").is_err());
+    calc.text_box.clear();
+    assert_eq!(calc.usage.get("dup"), Some(&4));
+    assert_eq!(calc.usage.get("+"), Some(&5), "a failed + was counted");
+    assert!(!calc.usage.contains_key("3*dup"));
+    assert!(calc.eval_line("stats:1").is_ok());
+    assert!(calc.overlay.as_ref().is_some_and(|o| o.lines.len() == 1 && o.lines[0].starts_with('+')));
+}
+
+// each workspace keeps its own stack and history, and switching back picks up where it was left
+#[test]
+fn workspaces_switch() {
+    let mut calc = calculator();
+    let first = calc.workspace.clone();
+    assert!(calc.eval_line("1 2").is_ok() && calc.eval_line("ws:test").is_ok());
+    assert_eq!(calc.workspace, "test");
+    assert!(calc.stack.is_empty() && calc.history.is_empty(), "a new workspace started with another's stack or history");
+    assert!(calc.eval_line("5").is_ok());
+    assert_eq!(calc.stack.len(), 1);
+    assert_eq!(calc.next_workspace().as_deref(), Some(first.as_str()));
+    assert!(calc.switch_workspace(&first).is_ok());
+    assert_eq!(calc.stack.len(), 2);
+    assert_eq!(calc.history.len(), 1);
+    assert!(calc.workspaces.get("test").is_some_and(|w| w.stack.len() == 1 && w.history == ["5"]));
+    assert!(calc.switch_workspace("").is_err());
+    // what's typed, and what lastx would put back, stay with their workspace
+    calc.stack = vec![Value::from(5.0), Value::from(2.0)];
+    assert!(calc.eval_line("-").is_ok());
+    calc.text_box = "12".into();
+    assert!(calc.switch_workspace("test").is_ok());
+    assert!(calc.text_box.is_empty() && calc.last_x.borrow().is_empty());
+    assert!(calc.eval_line(&format!("ws:{first}")).is_ok());
+    assert_eq!(calc.text_box, "12");
+    assert_eq!(*calc.last_x.borrow(), [Value::from(5.0), Value::from(2.0)]);
+}
+
+// a name ending in i is an operation, not a complex number, and constants like `pi` still run
+#[test]
+fn names_not_complex() {
+    let mut calc = calculator();
+    calc.operations.insert("i".into(), Operation::Macro(vec!["7".into()]));
+    assert!(calc.eval_line("i").is_ok() && calc.eval_line("pi").is_ok() && calc.eval_line("phi").is_ok());
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [7.0, PI, 1.618_033_988_749_895]);
+}
+
+// sto:x takes the top value into a register and rcl:x pushes it back; either fails with a message when there's nothing
+#[test]
+fn registers_store() {
+    let mut calc = calculator();
+    calc.stack = vec![Value::from(4.0)];
+    assert!(calc.eval_line("sto:test").is_ok() && calc.stack.is_empty());
+    assert!(calc.eval_line("rcl:test").is_ok());
+    assert_eq!(calc.stack, [Value::from(4.0)]);
+    calc.stack.clear();
+    assert!(matches!(calc.eval_line("sto:test"), Err(OpError::Domain(_))), "sto on an empty stack gave no error");
+    calc.text_box.clear();
+    assert!(calc.eval_line("rcl:nothing").is_err());
+}
+
+// a number just entered comes back into the text box as typed, but not once something has used it
+#[test]
+fn entries_pop_back() {
+    let mut calc = calculator();
+    assert!(calc.eval_line("2").is_ok() && calc.eval_line("1_000").is_ok());
+    assert!(calc.pop_to_entry().is_ok());
+    assert_eq!(calc.text_box, "1_000");
+    assert_eq!(calc.stack.len(), 1);
+    assert_eq!(calc.previous, "2");
+    calc.text_box.clear();
+    assert!(calc.pop_to_entry().is_err(), "the same entry came back twice");
+    assert!(calc.eval_line("3").is_ok() && calc.eval_line("dup").is_ok());
+    assert!(calc.pop_to_entry().is_err(), "a number that dup used came back");
+    assert_eq!(calc.stack.len(), 3);
+}
+
+// typing, backspace, and delete happen at the cursor, which can't leave the text box
+#[test]
+fn cursor_edits() {
+    let mut calc = calculator();
+    calc.text_box = "12×4".into();
+    calc.move_cursor(CursorMove::Left);
+    calc.move_cursor(CursorMove::Left);
+    calc.delete_back();
+    calc.insert_char('3');
+    assert_eq!(calc.text_box, "13×4");
+    assert_eq!(calc.cursor_index(), 2);
+    calc.delete_forward();
+    assert_eq!(calc.text_box, "134");
+    assert_eq!(calc.cursor_back, 1);
+    calc.move_cursor(CursorMove::Home);
+    calc.move_cursor(CursorMove::Left);
+    calc.delete_back();
+    assert_eq!(calc.text_box, "134");
+    assert_eq!(calc.cursor_index(), 0);
+    calc.move_cursor(CursorMove::End);
+    calc.move_cursor(CursorMove::Right);
+    calc.insert_char('5');
+    assert_eq!(calc.text_box, "1345");
+}
+
+// a count before `*` or after `;` runs the operation that many times, and a failure undoes every repeat
+#[test]
+fn repeats_run() {
+    let mut calc = calculator();
+    calc.stack = vec![Value::from(2.0)];
+    assert!(calc.eval_line("3*dup").is_ok());
+    assert_eq!(calc.stack.len(), 4);
+    assert!(calc.eval_line("+;3").is_ok());
+    assert_eq!(calc.stack, [Value::from(8.0)]);
+    calc.stack = vec![Value::from(1.0), Value::from(2.0), Value::from(3.0)];
+    assert!(calc.eval_line("3*+").is_err());
+    assert_eq!(calc.stack.len(), 3, "a failed repeat left a change");
+    calc.text_box.clear();
+    assert!(matches!(calc.eval_line("0*dup"), Err(OpError::Domain(_))));
+    calc.text_box.clear();
+    assert!(matches!(calc.eval_line("99999999999*dup"), Err(OpError::Domain(_))));
+    calc.text_box.clear();
+    calc.settings.max_stack = 10;
+    let error = calc.eval_line("1000000*dup").unwrap_err().to_string();
+    assert!(error.contains("would leave"), "the repeat wasn't stopped before it overflowed: {error}");
+    assert_eq!(calc.stack.len(), 3);
+}
+
+// everything after a lone `#` is ignored, and a line of only a comment does nothing
+#[test]
+fn comments_ignored() {
+    let mut calc = calculator();
+    assert!(calc.eval_line("3 4 + # sum them").is_ok());
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [7.0]);
+    let previous = calc.previous.clone();
+    assert!(calc.eval_line("  # nothing to do").is_ok());
+    assert_eq!(calc.stack.len(), 1);
+    assert_eq!(calc.previous, previous);
+    assert_eq!(strip_comment("a#b # c"), "a#b ");
+    assert_eq!(strip_comment("#x"), "#x");
+}
+
+// with --inline the window starts partway down the screen, and clicks are measured from its top
+#[test]
+fn inline_clicks() {
+    let mut calc = calculator();
+    calc.stack = vec![Value::from(1.0)];
+    let window = Rect { x: 0, y: 10, width: 40, height: 16 };
+    assert_eq!(calc.stack_index_at(window, 2, 11), Some(0));
+    assert_eq!(calc.stack_index_at(window, 2, 1), None);
+    assert_eq!(calc.stack_index_at(window, 2, 10), None);
+}
+
+// lastx puts back every operand of the last operation that took any, in order
+#[test]
+fn last_x_restores() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    calc.stack = vec![Value::from(5.0), Value::from(2.0)];
+    assert!(calc.operate("-".into(), tx.clone()) && calc.operate("dup".into(), tx.clone()) && calc.operate("lastx".into(), tx));
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [3.0, 3.0, 5.0, 2.0]);
+}
+
+// a result that equals an operand, or changes nothing, still leaves every operand in last_x
+#[test]
+fn same_result_restores() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    calc.stack = vec![Value::from(5.0), Value::from(0.0)];
+    assert!(calc.operate("+".into(), tx.clone()) && calc.operate("lastx".into(), tx.clone()));
+    assert_eq!(calc.stack, [Value::from(5.0), Value::from(5.0), Value::from(0.0)]);
+    calc.stack = vec![Value::from(3.0)];
+    assert!(calc.operate("abs".into(), tx.clone()) && calc.operate("lastx".into(), tx));
+    assert_eq!(calc.stack, [Value::from(3.0), Value::from(3.0)]);
+}
+
+// an operation that takes a count leaves the count and the values under it, and a macro leaves what its last operation took
+#[test]
+fn counted_restores() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    calc.stack = [9.0, 1.0, 2.0, 2.0].map(Value::from).to_vec();
+    assert!(calc.operate("sumn".into(), tx.clone()) && calc.operate("lastx".into(), tx.clone()));
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [9.0, 3.0, 1.0, 2.0, 2.0]);
+    assert!(calc.define("twice = dup +").is_ok());
+    calc.stack = vec![Value::from(4.0)];
+    assert!(calc.operate("twice".into(), tx.clone()) && calc.operate("lastx".into(), tx));
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [8.0, 4.0, 4.0]);
+}
+
+// EE only starts an exponent right after a number's digits, once
+#[test]
+fn exponent_entry() {
+    let mut calc = calculator();
+    calc.text_box = "2 -1.5".into();
+    assert!(calc.enter_exponent().is_ok());
+    assert_eq!(calc.text_box, "2 -1.5e");
+    assert_eq!(calc.exponent_hint().as_deref(), Some("-1.5 × 10^_"));
+    assert!(calc.enter_exponent().is_err(), "a second exponent was started");
+    calc.text_box.push('8');
+    assert_eq!(calc.parse_token(calc.current_word()), Some(Value::from(-1.5e8)));
+    for text in ["", "exp", "-", "1/2", "0x1f"] {
+        calc.text_box = text.into();
+        assert!(calc.enter_exponent().is_err(), "an exponent was started after `{text}`");
+    }
+}
+
+// EE and Tab work where the cursor is, leaving what's after it alone
+#[test]
+fn cursor_insertions() {
+    let mut calc = calculator();
+    calc.text_box = "3 swap".into();
+    calc.cursor_back = 5;
+    assert!(calc.enter_exponent().is_ok());
+    assert_eq!(calc.text_box, "3e swap");
+    calc.text_box = "dup 1".into();
+    calc.cursor_back = 3;
+    assert!(calc.enter_exponent().is_err(), "an exponent was started after `du`");
+    calc.text_box = "sw 2".into();
+    calc.cursor_back = 2;
+    calc.complete();
+    assert_eq!(calc.text_box, "swap 2");
+    assert_eq!(calc.cursor_back, 2);
+}
+
+// Enter with nothing typed follows empty_enter
+#[test]
+fn empty_enter_configurable() {
+    let mut calc = calculator();
+    calc.stack = vec![Value::from(2.0)];
+    calc.settings.empty_enter = EmptyEnterAction::DupTop;
+    assert!(calc.eval_line("").is_ok());
+    assert_eq!(calc.stack, [Value::from(2.0), Value::from(2.0)]);
+    calc.settings.empty_enter = EmptyEnterAction::Noop;
+    assert!(calc.eval_line("").is_ok());
+    assert_eq!(calc.stack.len(), 2);
+}
+
+// with empty_total set to identity, total and product of nothing push 0 and 1, but stackmax still fails
+#[test]
+fn empty_totals() {
+    let mut calc = calculator();
+    calc.empty_total.set(EmptyTotal::Identity);
+    assert_eq!(apply(&mut calc, "total", &[]), Some(vec![0.0]));
+    assert_eq!(apply(&mut calc, "product", &[]), Some(vec![1.0]));
+    assert_eq!(apply(&mut calc, "stackmax", &[]), None);
+    calc.empty_total.set(EmptyTotal::Error);
+    assert_eq!(apply(&mut calc, "total", &[]), None);
+    let (tx, _rx) = mpsc::channel();
+    let third = Value::parse("1/3").expect("1/3 should parse");
+    calc.stack = vec![third.clone(), third.clone(), third];
+    assert!(calc.operate("total".into(), tx));
+    assert_eq!(calc.stack, [Value::integer(1)], "adding thirds wasn't exact");
+}
+
+// numbers with units convert, scale, and add across units of the same kind, and refuse to mix kinds
+#[test]
+fn units_convert() {
+    let mut calc = calculator();
+    // adding across units goes through a conversion factor, so the sum is only compared to within rounding
+    assert!(calc.eval_line("1m 50cm +").is_ok());
+    assert_eq!(calc.stack.len(), 1);
+    assert!(calc.stack[0].approx_eq(&Value::Quantity(1.5, Unit::Meter), 1e-12));
+    assert!(calc.eval_line("1kg +").is_err(), "a mass was added to a length");
+    assert!(calc.eval_line("2 +").is_err(), "a plain number was added to a length");
+    calc.text_box.clear();
+    calc.stack.clear();
+    assert!(calc.eval_line("100degC c2f 2 * 6ft conv:ft:m").is_ok());
+    assert!(calc.stack[0].approx_eq(&Value::Quantity(424.0, Unit::Fahrenheit), 1e-9));
+    assert!(calc.stack[1].approx_eq(&Value::Quantity(1.8288, Unit::Meter), 1e-9));
+    assert!(calc.eval_line("1kg conv:kg:m").is_err());
+}
+
+// a value picked in stack edit mode can be dropped, or edited and put back in the same place
+#[test]
+fn stack_edits() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    calc.stack = [1.0, 2.0, 3.0, 4.0].iter().copied().map(Value::from).collect();
+    assert!(calc.toggle_stack_edit().is_ok());
+    assert_eq!(calc.selected, Some(3));
+    calc.move_selection(true, 10);
+    calc.delete_selected();
+    assert_eq!(calc.stack, [Value::from(1.0), Value::from(2.0), Value::from(4.0)]);
+    assert_eq!(calc.selected, Some(2));
+    calc.move_selection(true, 10);
+    calc.edit_selected();
+    assert_eq!(calc.text_box, "2.0");
+    assert!(calc.mode == Mode::Entry);
+    calc.text_box = "5 6".into();
+    submit(&mut calc, tx.clone());
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [1.0, 5.0, 6.0, 4.0]);
+    assert_eq!(calc.selected, None);
+    // a failed edit entry gives up the slot, so the next entry goes on top
+    calc.selected = Some(0);
+    calc.text_box = "nosuchop".into();
+    submit(&mut calc, tx.clone());
+    assert_eq!(calc.selected, None);
+    calc.text_box = "7".into();
+    submit(&mut calc, tx);
+    assert_eq!(calc.stack.last(), Some(&Value::integer(7)));
+}
+
+// exact arithmetic that overflows an i64 fraction still gives a result, but warns that it's a float now
+#[test]
+fn exactness_loss_warns() {
+    let mut calc = calculator();
+    let (tx, rx) = mpsc::channel();
+    let warned = |rx: &mpsc::Receiver<Event>| rx.try_iter().any(|event| matches!(event, Event::PushError(OpError::Warning(_))));
+    calc.stack = vec![Value::integer(i64::MAX), Value::integer(1)];
+    assert!(calc.operate("+".into(), tx.clone()));
+    assert!(!calc.stack[0].is_exact() && warned(&rx), "an overflowing + didn't warn");
+    calc.stack = vec![Value::integer(21)];
+    assert!(calc.operate("fact".into(), tx.clone()) && warned(&rx));
+    assert!(calc.eval_line("1/2 1/3 +").is_ok() && calc.errors.is_empty(), "exact + warned");
+    calc.stack = vec![Value::integer(1), Value::from(0.5)];
+    assert!(calc.operate("+".into(), tx.clone()) && !warned(&rx), "a float operand warned");
+    calc.stack = vec![Value::integer(2), Value::integer(3)];
+    assert!(calc.operate("/".into(), tx) && !warned(&rx));
+}
+
+// tofrac shows the closest fraction that fits the denominator limit without changing the value, or fails if none is close.
+// fromfrac pushes a typed fraction as a float
+#[test]
+fn fractions_approximate() {
+    let mut calc = calculator();
+    let (tx, rx) = mpsc::channel();
+    let mut tofrac = |n: f64| {
+        calc.stack = vec![Value::from(n)];
+        let shown = calc.operate("tofrac".into(), tx.clone());
+        assert!(calc.stack.len() == 1 && (calc.stack[0] == Value::from(n) || n.is_nan()), "tofrac changed the value");
+        let notice = rx.try_iter().find_map(|event| match event {
+            Event::PushError(OpError::Notice(notice)) => Some(notice),
+            _ => None,
+        });
+        shown.then(|| notice.expect("tofrac should show the fraction").rsplit(' ').next().unwrap_or_default().to_string())
+    };
+    assert_eq!(tofrac(PI).as_deref(), Some("355/113"));
+    assert_eq!(tofrac(0.1 + 0.2).as_deref(), Some("3/10"));
+    assert_eq!(tofrac(-2.5).as_deref(), Some("-5/2"));
+    assert_eq!(tofrac(7.0).as_deref(), Some("7"));
+    assert_eq!(tofrac(1e-9), None);
+    assert_eq!(tofrac(f64::NAN), None);
+    assert!(calc.eval_line("fromfrac:-7/2").is_ok());
+    assert_eq!(calc.stack.last(), Some(&Value::from(-3.5)));
+    assert!(calc.eval_line("fromfrac:1/0").is_err());
+    calc.text_box.clear();
+    assert!(calc.eval_line("fromfrac:3").is_err());
+}
+
+// with confirm_reset, one press only arms the reset, and it has to be pressed again before it runs out
+#[test]
+fn reset_confirms() {
+    let mut calc = calculator();
+    calc.settings.confirm_reset = true;
+    let start = Instant::now();
+    calc.stack = vec![Value::from(1.0)];
+    calc.request_reset(start);
+    assert!(calc.stack.len() == 1 && calc.armed_reset.is_some(), "the first press cleared the stack");
+    calc.request_reset(start + Duration::from_secs(1));
+    assert!(calc.stack.is_empty() && calc.armed_reset.is_none());
+    calc.stack = vec![Value::from(1.0)];
+    calc.request_reset(start);
+    let late = start + Duration::from_secs(10);
+    assert!(calc.expire_reset(late) && calc.armed_reset.is_none(), "the confirmation didn't expire");
+    calc.request_reset(late);
+    assert!(calc.stack.len() == 1 && calc.armed_reset.is_some());
+    calc.settings.confirm_reset = false;
+    calc.request_reset(late);
+    assert!(calc.stack.is_empty());
+}
+
+// the same table `--selftest` checks, on a calculator like the one it makes
+#[test]
+fn built_ins_match_table() {
+    assert!(selftest::run(false), "a built-in operation gave the wrong result; the failures are printed above");
+}
+
+// linreg fits two vectors of points, leaving the slope under the intercept, and linregr adds r² on top
+#[test]
+fn linreg_fits_vectors() {
+    let mut calc = calculator();
+    let (tx, _rx) = mpsc::channel();
+    let mut fit = |op: &str, x: Vec<f64>, y: Vec<f64>| {
+        calc.stack = vec![Value::Vector(x), Value::Vector(y)];
+        calc.operate(op.into(), tx.clone()).then(|| calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>())
+    };
+    // points on a line fit it exactly
+    assert_eq!(fit("linreg", vec![0.0, 1.0, 2.0], vec![1.0, 3.0, 5.0]), Some(vec![2.0, 1.0]));
+    assert_eq!(fit("linregr", vec![0.0, 1.0, 2.0], vec![1.0, 3.0, 5.0]), Some(vec![2.0, 1.0, 1.0]));
+    let scattered = fit("linregr", vec![1.0, 2.0, 3.0, 4.0], vec![2.0, 1.0, 4.0, 3.0]).expect("scattered points should fit");
+    assert!((scattered[2] - 0.36).abs() < 1e-12, "r² was {}", scattered[2]);
+    assert_eq!(fit("linreg", vec![1.0], vec![2.0]), None, "one point fit a line");
+    assert_eq!(fit("linreg", vec![3.0, 3.0], vec![1.0, 2.0]), None, "a vertical line was fit");
+    assert_eq!(fit("linreg", vec![1.0, 2.0, 3.0], vec![1.0, 2.0]), None, "vectors of different lengths were fit");
+    assert_eq!(fit("linreg", vec![1.0, 2.0], vec![4.0, 4.0]), Some(vec![0.0, 4.0]));
+    assert_eq!(fit("linregr", vec![1.0, 2.0], vec![4.0, 4.0]), None, "r² was given when every y is the same");
+    calc.stack = vec![Value::from(1e20), Value::from(2.0)];
+    assert!(!calc.operate("linreg".into(), tx.clone()), "linreg took plain numbers");
+    assert_eq!(calc.stack.len(), 2);
+}
+
+// fmt is a command, so it runs partway through a line and from a macro, and can't be redefined
+#[test]
+fn fmt_runs_anywhere() {
+    let mut calc = calculator();
+    assert!(calc.eval_line("7 4 1 fmt").is_ok());
+    assert!(calc.display_format == DisplayFormat::Fixed(4));
+    assert_eq!(calc.stack.len(), 1);
+    assert!(calc.define("sci = 2 2 fmt").is_ok() && calc.eval_line("sci").is_ok());
+    assert!(calc.display_format == DisplayFormat::Scientific(2));
+    assert!(calc.completions("fm").contains(&"fmt"));
+    assert!(calc.define("fmt = 1").is_err());
+}
+
+// base cycles from a line or a macro like any other command
+#[test]
+fn base_runs_anywhere() {
+    let mut calc = calculator();
+    assert!(calc.eval_line("255 base").is_ok());
+    assert!(calc.output_base == Base::Hexadecimal);
+    assert!(calc.define("tobin = base base").is_ok() && calc.eval_line("tobin").is_ok());
+    assert!(calc.output_base == Base::Binary);
+}
+
+// copyall is a command, so a line can end with it, and it fails without touching the clipboard when there's nothing to copy
+#[test]
+fn copyall_is_command() {
+    let mut calc = calculator();
+    assert!(calc.is_operation("copyall") && calc.completions("copy").contains(&"copyall"));
+    let error = calc.eval_line("1 drop copyall").unwrap_err().to_string();
+    assert!(error.contains("Nothing to copy"), "{error}");
+}
+
+// exportlua and importlua are commands, so a whole save and restore fits on one line
+#[test]
+fn lua_session_roundtrip() {
+    let mut calc = calculator();
+    let dir = std::env::temp_dir().join(format!("ripen-test-session-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("the temporary directory should be writable");
+    calc.data_dir = Some(dir.clone());
+    let result = calc.eval_line("1 2 sto:x 3 exportlua clear 5 sto:x importlua");
+    let _ = fs::remove_dir_all(&dir);
+    assert!(result.is_ok());
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [1.0, 3.0]);
+    assert_eq!(calc.registers.get("x").map(Value::to_f64), Some(2.0));
+}
+
+// window takes its size and operation after colons, so it works on a line and in a macro
+#[test]
+fn window_runs_anywhere() {
+    let mut calc = calculator();
+    assert!(calc.eval_line("1 2 3 4 window:2:+").is_ok());
+    assert_eq!(calc.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [3.0, 5.0, 7.0]);
+    assert!(calc.define("spread = window:3:+").is_ok());
+    assert!(calc.eval_line("spread").is_ok());
+    assert_eq!(calc.stack.len(), 1);
+    assert!(calc.eval_line("window:9:+").is_err());
+    calc.text_box.clear();
+    assert!(calc.eval_line("window:2").is_err());
+}
+
+// in the TUI a lone Lua function runs on its own thread, and the stack only changes when its result comes back
+#[test]
+fn background_call_finishes() {
+    let mut calc = calculator();
+    calc.background = true;
+    calc.load_lua("register(\"TestDouble\", 1, function(x) return x * 2 end)").expect("the Lua code should load");
+    let (tx, rx) = mpsc::channel();
+    calc.stack = vec![Value::from(4.0)];
+    calc.text_box = "testdouble".into();
+    submit(&mut calc, tx.clone());
+    assert!(calc.running.is_some(), "testdouble didn't run in the background");
+    assert_eq!(calc.stack, [Value::from(4.0)], "the stack changed before the result came back");
+    let finished = rx.iter().find_map(|event| match event {
+        Event::OperationResult(finished) => Some(finished),
+        _ => None,
+    }).expect("the call should send its result");
+    finish_background(&mut calc, *finished, tx);
+    assert!(calc.running.is_none());
+    assert_eq!(calc.stack, [Value::from(8.0)]);
+    assert!(calc.text_box.is_empty());
+}
+
+// Esc sets cancel, which stops a Lua function that would otherwise never return
+#[test]
+fn background_call_stops() {
+    let mut calc = calculator();
+    calc.background = true;
+    calc.operation_timeout = None;
+    calc.load_lua("register(\"TestForever\", 1, function(x) while true do end end)").expect("the Lua code should load");
+    let (tx, rx) = mpsc::channel();
+    calc.stack = vec![Value::from(1.0)];
+    calc.text_box = "testforever".into();
+    submit(&mut calc, tx.clone());
+    calc.cancel.store(true, Ordering::Relaxed);
+    let finished = rx.iter().find_map(|event| match event {
+        Event::OperationResult(finished) => Some(finished),
+        _ => None,
+    }).expect("the call should stop and send its result");
+    finish_background(&mut calc, *finished, tx);
+    assert_eq!(calc.stack, [Value::from(1.0)], "a stopped call changed the stack");
+    assert_eq!(calc.text_box, "testforever");
+    assert!(!calc.cancel.load(Ordering::Relaxed), "the next call would stop straight away");
+    assert!(rx.try_iter().any(|event| matches!(event, Event::PushError(OpError::LuaError(_)))), "stopping wasn't reported");
+}
+
+// a max_stack lowered since the session was saved applies to every workspace, not just the current one
+#[test]
+fn restore_trims_workspaces() {
+    let mut calc = calculator();
+    let dir = std::env::temp_dir().join(format!("ripen-test-restore-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("the temporary directory should be writable");
+    calc.data_dir = Some(dir.clone());
+    assert!(calc.eval_line("1 2 3 4").is_ok());
+    assert!(calc.eval_line("ws:other").is_ok());
+    assert!(calc.eval_line("5 6 7").is_ok());
+    let saved = calc.save_session();
+    let mut restored = calculator();
+    restored.data_dir = Some(dir.clone());
+    restored.settings.max_stack = 2;
+    let result = restored.restore_session();
+    let _ = fs::remove_dir_all(&dir);
+    assert!(saved.is_ok() && result.is_ok());
+    assert_eq!(restored.stack.iter().map(Value::to_f64).collect::<Vec<_>>(), [6.0, 7.0]);
+    let parked = restored.workspaces.values().map(|w| w.stack.iter().map(Value::to_f64).collect::<Vec<_>>()).collect::<Vec<_>>();
+    assert_eq!(parked, [[3.0, 4.0]]);
+}
@@ -0,0 +1,145 @@
+use ratatui::{
+    style::{Color, Style},
+    widgets::{Block, BorderType, Borders},
+};
+use serde::Deserialize;
+
+// the `[theme]` table in config.toml. anything left out comes from the preset
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub preset: Preset,
+    pub border_type: Option<BorderStyle>,
+    pub border_color: Option<ThemeColor>,
+    pub text_color: Option<ThemeColor>,
+    pub error_color: Option<ThemeColor>,
+    // errors from Lua and Uiua functions
+    pub script_error_color: Option<ThemeColor>,
+    // stack values, with color_by_sign on
+    pub negative_color: Option<ThemeColor>,
+    // infinities and NaN, with color_by_sign on
+    pub warning_color: Option<ThemeColor>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    #[default]
+    Dark,
+    Light,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderStyle {
+    Rounded,
+    Plain,
+    Double,
+    Thick,
+}
+
+// a color name like `lightred`, or `#rrggbb`
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ThemeColor(Color);
+
+impl TryFrom<String> for ThemeColor {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, String> {
+        if let Some(hex) = name.strip_prefix('#').filter(|hex| hex.len() == 6) {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("`{name}` is not a valid color"));
+            return Ok(Self(Color::Rgb(channel(0)?, channel(2)?, channel(4)?)));
+        }
+        Ok(Self(match name.to_lowercase().replace(['_', '-', ' '], "").as_str() {
+            "default" | "reset" => Color::Reset,
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "gray" | "grey" => Color::Gray,
+            "darkgray" | "darkgrey" => Color::DarkGray,
+            "lightred" => Color::LightRed,
+            "lightgreen" => Color::LightGreen,
+            "lightyellow" => Color::LightYellow,
+            "lightblue" => Color::LightBlue,
+            "lightmagenta" => Color::LightMagenta,
+            "lightcyan" => Color::LightCyan,
+            "white" => Color::White,
+            _ => return Err(format!("`{name}` is not a valid color")),
+        }))
+    }
+}
+
+pub struct Theme {
+    pub border_type: BorderType,
+    pub border: Style,
+    pub text: Style,
+    pub error: Style,
+    pub script_error: Style,
+    pub negative: Style,
+    pub warning: Style,
+}
+
+impl Theme {
+    fn preset(preset: Preset) -> Self {
+        let (border, text, error, script_error, negative, warning) = match preset {
+            Preset::Dark => (Color::Reset, Color::Reset, Color::LightRed, Color::LightYellow, Color::Red, Color::Yellow),
+            Preset::Light => (Color::DarkGray, Color::Black, Color::Red, Color::Magenta, Color::Red, Color::Magenta),
+        };
+        Self {
+            border_type: BorderType::Rounded,
+            border: Style::default().fg(border),
+            text: Style::default().fg(text),
+            error: Style::default().fg(error),
+            script_error: Style::default().fg(script_error),
+            negative: Style::default().fg(negative),
+            warning: Style::default().fg(warning),
+        }
+    }
+
+    pub fn from_config(config: &ThemeConfig) -> Self {
+        let mut theme = Self::preset(config.preset);
+        if let Some(border_type) = config.border_type {
+            theme.border_type = match border_type {
+                BorderStyle::Rounded => BorderType::Rounded,
+                BorderStyle::Plain => BorderType::Plain,
+                BorderStyle::Double => BorderType::Double,
+                BorderStyle::Thick => BorderType::Thick,
+            };
+        }
+        if let Some(ThemeColor(color)) = config.border_color {
+            theme.border = theme.border.fg(color);
+        }
+        if let Some(ThemeColor(color)) = config.text_color {
+            theme.text = theme.text.fg(color);
+        }
+        if let Some(ThemeColor(color)) = config.error_color {
+            theme.error = theme.error.fg(color);
+        }
+        if let Some(ThemeColor(color)) = config.script_error_color {
+            theme.script_error = theme.script_error.fg(color);
+        }
+        if let Some(ThemeColor(color)) = config.negative_color {
+            theme.negative = theme.negative.fg(color);
+        }
+        if let Some(ThemeColor(color)) = config.warning_color {
+            theme.warning = theme.warning.fg(color);
+        }
+        theme
+    }
+
+    // every bordered box uses this
+    pub fn block(&self) -> Block<'static> {
+        Block::default().borders(Borders::ALL).border_type(self.border_type).border_style(self.border)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::preset(Preset::default())
+    }
+}
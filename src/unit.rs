@@ -0,0 +1,110 @@
+// units that can be attached to a number, like `3ft`. each converts to others of the same dimension
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Unit {
+    Millimeter,
+    Centimeter,
+    Meter,
+    Kilometer,
+    Inch,
+    Foot,
+    Yard,
+    Mile,
+    Gram,
+    Kilogram,
+    Ounce,
+    Pound,
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Temperature,
+}
+
+const UNITS: &[(&str, Unit)] = &[
+    ("mm", Unit::Millimeter),
+    ("cm", Unit::Centimeter),
+    ("m", Unit::Meter),
+    ("km", Unit::Kilometer),
+    ("in", Unit::Inch),
+    ("ft", Unit::Foot),
+    ("yd", Unit::Yard),
+    ("mi", Unit::Mile),
+    ("g", Unit::Gram),
+    ("kg", Unit::Kilogram),
+    ("oz", Unit::Ounce),
+    ("lb", Unit::Pound),
+    ("degc", Unit::Celsius),
+    ("degf", Unit::Fahrenheit),
+    ("k", Unit::Kelvin),
+];
+
+impl Unit {
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        UNITS.iter().find(|(n, _)| *n == name).map(|&(_, unit)| unit)
+    }
+
+    // what it's typed and shown as
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Celsius => "degC",
+            Self::Fahrenheit => "degF",
+            Self::Kelvin => "K",
+            // unwrap safety: every unit is in the table
+            unit => UNITS.iter().find(|(_, u)| *u == unit).unwrap().0,
+        }
+    }
+
+    pub fn dimension(self) -> Dimension {
+        match self {
+            Self::Millimeter | Self::Centimeter | Self::Meter | Self::Kilometer
+                | Self::Inch | Self::Foot | Self::Yard | Self::Mile => Dimension::Length,
+            Self::Gram | Self::Kilogram | Self::Ounce | Self::Pound => Dimension::Mass,
+            Self::Celsius | Self::Fahrenheit | Self::Kelvin => Dimension::Temperature,
+        }
+    }
+
+    // (scale, offset) so that base = value * scale + offset. the bases are meters, kilograms, and kelvin
+    fn to_base(self) -> (f64, f64) {
+        match self {
+            Self::Millimeter => (0.001, 0.0),
+            Self::Centimeter => (0.01, 0.0),
+            Self::Meter => (1.0, 0.0),
+            Self::Kilometer => (1000.0, 0.0),
+            Self::Inch => (0.0254, 0.0),
+            Self::Foot => (0.3048, 0.0),
+            Self::Yard => (0.9144, 0.0),
+            Self::Mile => (1609.344, 0.0),
+            Self::Gram => (0.001, 0.0),
+            Self::Kilogram => (1.0, 0.0),
+            Self::Ounce => (0.028_349_523_125, 0.0),
+            Self::Pound => (0.453_592_37, 0.0),
+            Self::Celsius => (1.0, 273.15),
+            Self::Fahrenheit => (5.0 / 9.0, 273.15 - 32.0 * 5.0 / 9.0),
+            Self::Kelvin => (1.0, 0.0),
+        }
+    }
+
+    pub fn convert(self, value: f64, to: Self) -> Result<f64, String> {
+        if self.dimension() != to.dimension() {
+            return Err(format!("Can't convert {} to {}", self.name(), to.name()));
+        }
+        let (scale, offset) = self.to_base();
+        let (to_scale, to_offset) = to.to_base();
+        Ok((value * scale + offset - to_offset) / to_scale)
+    }
+}
+
+// splits `3.5ft` into the number and the unit. None if the letters at the end aren't a unit
+pub fn split_quantity(text: &str) -> Option<(&str, Unit)> {
+    let start = text.trim_end_matches(|c: char| c.is_ascii_alphabetic()).len();
+    if start == 0 || start == text.len() {
+        return None;
+    }
+    Some((&text[..start], Unit::parse(&text[start..])?))
+}
@@ -0,0 +1,110 @@
+use crate::{number::Number, unit::{split_quantity, Unit}};
+
+// what the stack holds. operations on numbers fail when given a vector, and most fail when given a unit
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Scalar(Number),
+    Vector(Vec<f64>),
+    // a real number with a unit, like `3ft`
+    Quantity(f64, Unit),
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Self {
+        Self::Scalar(n)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Self::Scalar(n.into())
+    }
+}
+
+impl Value {
+    pub fn integer(n: i64) -> Self {
+        Self::Scalar(Number::integer(n))
+    }
+
+    pub fn scalar(&self) -> Option<Number> {
+        match self {
+            Self::Scalar(n) => Some(*n),
+            Self::Vector(_) | Self::Quantity(..) => None,
+        }
+    }
+
+    pub fn as_real(&self) -> Option<f64> {
+        self.scalar()?.as_real()
+    }
+
+    // an exact fraction or integer, as opposed to a float
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Self::Scalar(Number::Rational(_)))
+    }
+
+    pub fn is_finite(&self) -> bool {
+        match self {
+            Self::Scalar(n) => n.is_finite(),
+            Self::Vector(v) => v.iter().all(|n| n.is_finite()),
+            Self::Quantity(n, _) => n.is_finite(),
+        }
+    }
+
+    // real numbers, and the elements of vectors, are equal if they're within `epsilon`.
+    // an epsilon of 0 is exact. anything else has to match exactly
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        let close = |a: f64, b: f64| a == b || (a - b).abs() <= epsilon;
+        match (self, other) {
+            (Self::Vector(a), Self::Vector(b)) => a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| close(a, b)),
+            (Self::Quantity(a, unit), Self::Quantity(b, other)) => unit == other && close(*a, *b),
+            (a, b) => match (a.as_real(), b.as_real()) {
+                (Some(a), Some(b)) => close(a, b),
+                _ => a == b,
+            },
+        }
+    }
+
+    // NaN for vectors, complex numbers, and numbers with units, so they fail any range check on the result
+    pub fn to_f64(&self) -> f64 {
+        self.as_real().unwrap_or(f64::NAN)
+    }
+
+    // a number, a number with a unit, or real numbers in brackets like `[1 2.5 3]`
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            Some(inner) => inner.split_whitespace()
+                .map(|n| Number::parse(n)?.as_real())
+                .collect::<Option<_>>()
+                .map(Self::Vector),
+            None => Number::parse(text).map(Self::Scalar).or_else(|| Self::parse_quantity(text)),
+        }
+    }
+
+    // a real number followed directly by a unit, like `3ft` or `-40degC`
+    pub fn parse_quantity(text: &str) -> Option<Self> {
+        let (number, unit) = split_quantity(text)?;
+        Some(Self::Quantity(Number::parse(number)?.as_real()?, unit))
+    }
+
+    // parses back to exactly the same value
+    pub fn to_literal(&self) -> String {
+        match self {
+            Self::Scalar(n) => n.to_literal(),
+            Self::Vector(v) => format!("[{}]", v.iter().map(|n| format!("{n:?}")).collect::<Vec<_>>().join(" ")),
+            Self::Quantity(n, unit) => format!("{n:?}{}", unit.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+    use crate::unit::Unit;
+
+    #[test]
+    fn units_parse() {
+        assert_eq!(Value::parse("-40degC"), Some(Value::Quantity(-40.0, Unit::Celsius)));
+        assert_eq!(Value::parse("3FT"), Some(Value::Quantity(3.0, Unit::Foot)));
+        assert_eq!(Value::parse("ft"), None);
+    }
+}